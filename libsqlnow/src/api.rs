@@ -25,6 +25,9 @@ pub fn configure(service_config: &mut ServiceConfig) {
         .service(list_inputs)
         .service(create_input)
         .service(delete_input)
+        .service(refresh_input)
+        .service(profile_table)
+        .service(search)
         .service(events);
 }
 
@@ -157,7 +160,11 @@ async fn delete_query(app_data: web::Data<AppData>, name: web::Path<String>) ->
 #[derive(Debug, Clone, Deserialize)]
 struct NewInput {
     /// A file path, or a `postgresql://` / `sqlite://` URI.
+    #[serde(default)]
     uri: String,
+    /// Instead of a uri, the SQL of a view over what is already attached:
+    /// `{"name": "clean_orders", "sql": "SELECT ... FROM raw"}`.
+    sql: Option<String>,
     /// What to call it. Defaults to the file stem, as on the command line.
     #[serde(alias = "as")]
     name: Option<String>,
@@ -169,6 +176,10 @@ struct NewInput {
     only: Vec<String>,
     #[serde(default)]
     except: Vec<String>,
+    /// How to read the file, as `--with` takes on the command line:
+    /// `{"delim": ";", "header": false, "types": {"id": "BIGINT"}}`.
+    #[serde(default)]
+    options: crate::InputOptions,
 }
 
 /// Which session this server is serving.
@@ -250,13 +261,20 @@ async fn list_inputs(app_data: web::Data<AppData>) -> HttpResponse {
             let recorded: Vec<_> = inputs
                 .iter()
                 .map(|(kind, input)| {
-                    serde_json::json!({
+                    let mut recorded = serde_json::json!({
                         "kind": kind,
                         "name": input.name,
                         "uri": input.uri,
                         "only": input.tables,
                         "except": input.except,
-                    })
+                        "options": input.options,
+                    });
+                    // a view defined by sql keeps its sql where a uri would be
+                    if kind == "sql" {
+                        recorded["sql"] = recorded["uri"].take();
+                        recorded.as_object_mut().expect("an object").remove("uri");
+                    }
+                    recorded
                 })
                 .collect();
             HttpResponse::Ok().json(serde_json::json!({ "inputs": recorded }))
@@ -269,7 +287,8 @@ async fn list_inputs(app_data: web::Data<AppData>) -> HttpResponse {
 ///
 /// The table appears in the UI without a restart, and is recorded so later
 /// launches replay it. With a main database, a view or table created here
-/// lives in that file, so only database attaches need recording.
+/// lives in that file, so only database attaches need recording — and views
+/// defined by SQL, whose definition is recorded so the session can list it.
 #[post("/api/inputs")]
 async fn create_input(app_data: web::Data<AppData>, body: web::Json<NewInput>) -> HttpResponse {
     let kind = body.kind.clone().unwrap_or_else(|| "view".to_string());
@@ -277,26 +296,48 @@ async fn create_input(app_data: web::Data<AppData>, body: web::Json<NewInput>) -
         return HttpResponse::BadRequest()
             .json(serde_json::json!({ "error": "kind must be \"view\" or \"table\"" }));
     }
+    let bad_request =
+        |error: &str| HttpResponse::BadRequest().json(serde_json::json!({ "error": error }));
 
     let mut input = crate::Input {
         name: body.name.clone().unwrap_or_default(),
         uri: body.uri.clone(),
         tables: body.only.clone(),
         except: body.except.clone(),
+        options: body.options.clone(),
+        ..Default::default()
+    };
+    // a view defined by SQL has no file to name it after or to check
+    let kind = match &body.sql {
+        Some(_) if !body.uri.is_empty() => return bad_request("give a uri or sql, not both"),
+        Some(_) if kind != "view" => return bad_request("a view defined by sql cannot be a table"),
+        Some(sql) => {
+            input.uri = sql.clone();
+            "sql".to_string()
+        }
+        None => {
+            // fills in a missing name from the file stem, and reports a path
+            // that is not there before anything is attached
+            if let Err(e) = crate::default_name_and_check(&mut input) {
+                return bad_request(&e.to_string());
+            }
+            // recorded with the choice --text made for it, as it would be
+            // had it been given on the command line
+            input.settle_text(app_data.all_text);
+            kind
+        }
     };
-    // fills in a missing name from the file stem, and reports a path that is
-    // not there before anything is attached
-    if let Err(e) = crate::default_name_and_check(&mut input) {
-        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }));
-    }
 
-    if let Err(e) = crate::add_input(&app_data, &kind, &input).await {
-        return HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }));
-    }
+    let attached = match crate::add_input(&app_data, &kind, &input).await {
+        Ok(attached) => attached,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    };
 
     // a file view or table with a main database persists in that database, so
     // only attaches have to be replayed
-    if app_data.db.is_none() || input.is_database() {
+    if app_data.db.is_none() || kind == "sql" || input.is_database() {
         if let Ok(session) = app_data.session.lock() {
             if let Err(e) = session.add_input(&kind, &input) {
                 eprintln!("Attached {} but could not record it: {}", input.name, e);
@@ -305,11 +346,21 @@ async fn create_input(app_data: web::Data<AppData>, body: web::Json<NewInput>) -
     }
     app_data.session_version.fetch_add(1, Ordering::Relaxed);
 
-    HttpResponse::Created().json(serde_json::json!({
+    let mut created = serde_json::json!({
         "name": input.name,
         "kind": kind,
         "uri": input.uri,
-    }))
+        "options": input.options,
+    });
+    if kind == "sql" {
+        created["sql"] = created["uri"].take();
+        created.as_object_mut().expect("an object").remove("uri");
+    }
+    // only for an input read with rejects=true, where 0 is news too
+    if let Some(rejected) = attached.rejected {
+        created["rejected"] = rejected.into();
+    }
+    HttpResponse::Created().json(created)
 }
 
 /// Detach an input by name, dropping its view or table.
@@ -327,6 +378,62 @@ async fn delete_input(app_data: web::Data<AppData>, name: web::Path<String>) ->
     HttpResponse::NoContent().finish()
 }
 
+/// Read a table input again from its source file. Its rows change but the
+/// session does not, so /api/events says `refreshed` rather than `changed`.
+#[post("/api/inputs/{name}/refresh")]
+async fn refresh_input(app_data: web::Data<AppData>, name: web::Path<String>) -> HttpResponse {
+    let known = app_data
+        .loaded
+        .lock()
+        .map(|loaded| loaded.contains_key(name.as_str()))
+        .unwrap_or(false);
+    let attached = match crate::refresh_input(&app_data, &name).await {
+        Ok(attached) => attached,
+        Err(e) if !known => {
+            return HttpResponse::NotFound().json(serde_json::json!({ "error": e.to_string() }))
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    };
+    let mut refreshed = serde_json::json!({ "name": name.as_str(), "kind": "table" });
+    if let Some(rejected) = attached.rejected {
+        refreshed["rejected"] = rejected.into();
+    }
+    HttpResponse::Ok().json(refreshed)
+}
+
+/// A profile of every column of a table, by its name in the sidebar: min,
+/// max, distinct count, nulls, quartiles and the most frequent values.
+#[get("/api/tables/{name}/profile")]
+async fn profile_table(app_data: web::Data<AppData>, name: web::Path<String>) -> HttpResponse {
+    match crate::profile_table(&app_data, &name).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": format!("no table is named \"{}\"", name) })),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<usize>,
+}
+
+/// Find tables and columns by name or type across every input, fuzzily and
+/// best first: `/api/search?q=customer email`.
+#[get("/api/search")]
+async fn search(app_data: web::Data<AppData>, params: web::Query<SearchParams>) -> HttpResponse {
+    if params.q.trim().is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": "nothing to search for: give ?q=" }));
+    }
+    match crate::search_catalog(&app_data, &params.q, params.limit).await {
+        Ok(hits) => HttpResponse::Ok().json(serde_json::json!({ "results": hits })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
 /// This server's own writes (the counter) plus anyone else's (the session's
 /// `changed_at`, which an external writer moves too).
 fn session_stamp(app_data: &AppData) -> (u64, Option<i64>) {
@@ -341,20 +448,30 @@ fn session_stamp(app_data: &AppData) -> (u64, Option<i64>) {
 
 /// Server-sent events: emits `data: changed` (within ~1s) whenever the
 /// session changes — through this API, through /query.json history, or
-/// through an external writer touching the sidecar file.
+/// through an external writer touching the sidecar file — and
+/// `data: refreshed` when a table input was read again, on request or by
+/// `--watch`.
 #[get("/api/events")]
 async fn events(app_data: web::Data<AppData>) -> HttpResponse {
     let event_stream = stream! {
         yield Ok::<Bytes, actix_web::Error>(Bytes::from_static(b"retry: 2000\n\n"));
         let mut last = session_stamp(&app_data);
+        let mut last_refresh = app_data.refresh_version.load(Ordering::Relaxed);
         let mut quiet_ticks: u32 = 0;
         loop {
             tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
             let now = session_stamp(&app_data);
+            let refresh = app_data.refresh_version.load(Ordering::Relaxed);
             if now != last {
+                // the grid re-runs on either, so one message covers both
                 last = now;
+                last_refresh = refresh;
                 quiet_ticks = 0;
                 yield Ok(Bytes::from_static(b"data: changed\n\n"));
+            } else if refresh != last_refresh {
+                last_refresh = refresh;
+                quiet_ticks = 0;
+                yield Ok(Bytes::from_static(b"data: refreshed\n\n"));
             } else {
                 quiet_ticks += 1;
                 if quiet_ticks >= 15 {
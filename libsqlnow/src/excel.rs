@@ -1,8 +1,17 @@
 use duckdb::Connection;
 use duckdb::appender_params_from_iter;
-use calamine::{open_workbook, Reader, Xlsx, Data};
+use calamine::{open_workbook_auto, Reader, Data, ExcelDateTime, Range};
 use eyre::Result;
+use std::collections::{BTreeSet, HashSet};
 
+use crate::session::{quote_ident, quote_literal};
+use crate::{loaded_comment, InputOptions};
+
+/// Settles on one duckdb type for a column from the cells in it. Cells that
+/// disagree widen it as little as they can — whole and fractional numbers to
+/// DOUBLE, dates and timestamps to TIMESTAMP — and anything else that is
+/// mixed, a stray "n/a" among numbers say, makes it TEXT, which every cell
+/// can be loaded as.
 struct Detector {
     db_type: &'static str,
 }
@@ -15,17 +24,7 @@ impl Detector {
     }
 
     fn add(&mut self, cell: &Data) {
-        if self.db_type == "TEXT" {
-            return;
-        }
-        let db_type = db_type(cell);
-        if !self.db_type.is_empty() {
-            if self.db_type != db_type {
-                self.db_type = "TEXT";
-            }
-        } else {
-            self.db_type = db_type;
-        }
+        self.db_type = widen(self.db_type, db_type(cell));
     }
 
     fn detect(&self) -> &str {
@@ -37,8 +36,16 @@ impl Detector {
     }
 }
 
-pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, connection: &Connection) -> Result<()> {
-    let mut workbook: Xlsx<_> = open_workbook(file)?;
+/// Load a spreadsheet's sheets as tables: one table named after the input when
+/// there is a single sheet (or a single one is asked for), otherwise a schema
+/// with a table per sheet. calamine picks the reader from the extension, so
+/// .xlsx, .xlsm, .xlsb, .xls and .ods all come through here alike.
+///
+/// The options say where in each sheet the table is: the `range` of cells to
+/// read, the `header_row` that names the columns, or `header = false` for
+/// none at all. Without them the header is the first row with anything in it.
+pub fn load_workbook(file: &str, name: &str, tables: &Vec<String>, options: &InputOptions, drop: bool, connection: &Connection) -> Result<()> {
+    let mut workbook = open_workbook_auto(file)?;
     let sheet_names = workbook.sheet_names().to_owned();
 
     let path = std::path::PathBuf::from(file);
@@ -56,6 +63,8 @@ pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, conne
         connection.execute(&format!("CREATE SCHEMA IF NOT EXISTS \"{schema_or_table}\" "), [])?;
     }
 
+    let mut typed = BTreeSet::new();
+
     for sheet_name in sheet_names.iter() {
 
         if !tables.is_empty() && !tables.contains(&sheet_name) {
@@ -68,32 +77,30 @@ pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, conne
             format!("\"{}\".\"{}\"", schema_or_table, sheet_name)
         };
 
-        let range = workbook.worksheet_range(&sheet_name)?;
-
-        let mut columns = vec![];
-        let mut rows = vec![];
+        let range = select_cells(workbook.worksheet_range(&sheet_name)?, options, &sheet_name)?;
+        let mut input_rows = range.rows();
 
-        let mut detectors = vec![];
-
-        for (num, input_row) in range.rows().enumerate() {
-
-            if num == 0 {
-                for cell in input_row.iter() {
-                    let value = data_to_string(cell);
-                    if value.is_empty() {
-                        columns.push(format!("column-{num}"));
-                        continue;
-                    }
-                    columns.push(value);
-                    detectors.push(Detector::new());
-                }
-                continue;
+        let columns = if options.header == Some(false) {
+            if options.header_row.is_some() {
+                return Err(eyre::eyre!("header_row names a header, so it cannot go with header = false"));
             }
+            column_names(None, range.width())
+        } else {
+            let header_at = header_offset(&range, options, &sheet_name)?;
+            column_names(input_rows.nth(header_at), range.width())
+        };
+        if columns.is_empty() {
+            return Err(eyre::eyre!("Sheet \"{}\" of {} has no cells to read", sheet_name, file));
+        }
 
+        let mut detectors: Vec<Detector> = columns.iter().map(|_| Detector::new()).collect();
+        let mut rows = vec![];
+
+        for input_row in input_rows {
             let mut row = vec![];
             for (col_num, cell) in input_row.iter().enumerate() {
                 let value = data_to_string(cell);
-                if value.is_empty() {
+                if value.trim().is_empty() {
                     row.push(None);
                 } else {
                     row.push(Some(value));
@@ -110,8 +117,14 @@ pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, conne
         let mut create_table = format!("CREATE TABLE {} (", table_name);
         for (num, column) in columns.iter().enumerate() {
 
-            let db_type = detectors[num].detect();
-            let col_name = format!("\"{}\"", column);
+            let db_type = match options.types.get(column) {
+                Some(db_type) => {
+                    typed.insert(column.clone());
+                    db_type.as_str()
+                }
+                None => detectors[num].detect(),
+            };
+            let col_name = quote_ident(column);
             
             if num == columns.len() - 1 {
                 create_table.push_str(&format!("{col_name} {db_type} NULL"));
@@ -122,6 +135,9 @@ pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, conne
         create_table.push_str(");");
 
         connection.execute(&create_table, [])?;
+        if !single_sheet {
+            connection.execute_batch(&format!("COMMENT ON TABLE {} IS {};", table_name, quote_literal(&loaded_comment(schema_or_table))))?;
+        }
 
         let mut appender = if single_sheet {
             connection.appender(&schema_or_table)?
@@ -129,23 +145,132 @@ pub fn load_xlsx(file: &str, name: &str, tables: &Vec<String>, drop: bool, conne
             connection.appender_to_db(&sheet_name, &schema_or_table)?  
         }; 
 
-        for row in rows {
-            appender.append_row(appender_params_from_iter(row))?;
+        for (num, row) in rows.into_iter().enumerate() {
+            appender.append_row(appender_params_from_iter(row)).map_err(|e| {
+                eyre::eyre!("Could not load row {} of sheet \"{}\": {}", num + 1, sheet_name, e)
+            })?;
         }
         appender.flush()?;
     }
+
+    // a type for a column no sheet has is most likely a misspelt name
+    let untyped: Vec<&String> = options.types.keys().filter(|column| !typed.contains(*column)).collect();
+    if !untyped.is_empty() {
+        return Err(eyre::eyre!(
+            "types names columns {} has none of: {}",
+            file,
+            untyped.iter().map(|column| column.as_str()).collect::<Vec<_>>().join(", ")
+        ));
+    }
     Ok(())
 }
 
+/// The part of a sheet the `range` option names, or the whole of it.
+fn select_cells(range: Range<Data>, options: &InputOptions, sheet_name: &str) -> Result<Range<Data>> {
+    let Some(cells) = &options.range else {
+        return Ok(range);
+    };
+    let (first, last) = match cells.split_once(':') {
+        Some((first, last)) => (cell_position(first)?, Some(cell_position(last)?)),
+        None => (cell_position(cells)?, None),
+    };
+    // an open range runs to the last used cell, so it is empty if that is
+    // above or left of where it starts
+    let last = match (last, range.end()) {
+        (Some(last), _) => last,
+        (None, Some(end)) if end.0 >= first.0 && end.1 >= first.1 => end,
+        (None, _) => {
+            return Err(eyre::eyre!("Sheet \"{}\" has no cells from {} on", sheet_name, cells));
+        }
+    };
+    if last.0 < first.0 || last.1 < first.1 {
+        return Err(eyre::eyre!("The range {} ends before it starts", cells));
+    }
+    Ok(range.range(first, last))
+}
+
+/// A cell in A1 notation as calamine's zero-based (row, column).
+fn cell_position(cell: &str) -> Result<(u32, u32)> {
+    let cell = cell.trim().to_ascii_uppercase();
+    let split = cell.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(cell.len());
+    let (letters, digits) = cell.split_at(split);
+    let row: u32 = digits.parse().unwrap_or(0);
+    if letters.is_empty() || letters.len() > 3 || row == 0 {
+        return Err(eyre::eyre!("\"{}\" is not a cell like B3", cell));
+    }
+    let column = letters.bytes().fold(0, |column, letter| column * 26 + u32::from(letter - b'A') + 1);
+    Ok((row - 1, column - 1))
+}
+
+/// How many rows into the selected cells the header is: none, unless
+/// `header_row` puts it further down.
+fn header_offset(range: &Range<Data>, options: &InputOptions, sheet_name: &str) -> Result<usize> {
+    let Some(header_row) = options.header_row else {
+        return Ok(0);
+    };
+    let first = range.start().map(|(row, _)| row + 1).unwrap_or(1);
+    let last = range.end().map(|(row, _)| row + 1).unwrap_or(0);
+    if header_row < first || header_row > last {
+        return Err(eyre::eyre!(
+            "header_row {} is outside the cells read from sheet \"{}\" (rows {} to {})",
+            header_row, sheet_name, first, last
+        ));
+    }
+    Ok((header_row - first) as usize)
+}
+
+/// A name for each of `width` columns: the header's text, or column_N (its
+/// place, from 1) where the header cell is blank or there is no header. A
+/// name already taken — duckdb does not tell case apart — gets _2, _3 and so
+/// on, so a report with two "Total" columns still loads.
+fn column_names(header: Option<&[Data]>, width: usize) -> Vec<String> {
+    let mut taken = HashSet::new();
+    (0..width)
+        .map(|num| {
+            let text = header.and_then(|cells| cells.get(num)).map(data_to_string).unwrap_or_default();
+            let name = match text.trim() {
+                "" => format!("column_{}", num + 1),
+                text => text.to_string(),
+            };
+            let mut unique = name.clone();
+            let mut copy = 2;
+            while !taken.insert(unique.to_lowercase()) {
+                unique = format!("{}_{}", name, copy);
+                copy += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+fn widen(seen: &'static str, cell: &'static str) -> &'static str {
+    match (seen, cell) {
+        (seen, "") => seen,
+        ("", cell) => cell,
+        (seen, cell) if seen == cell => seen,
+        ("BIGINT", "DOUBLE") | ("DOUBLE", "BIGINT") => "DOUBLE",
+        ("DATE", "TIMESTAMP") | ("TIMESTAMP", "DATE") => "TIMESTAMP",
+        _ => "TEXT",
+    }
+}
+
+/// A cell as the text duckdb casts into its column. Dates and times come out
+/// as ISO text rather than Excel's day count, in a TEXT column as much as in
+/// a DATE one.
 fn data_to_string(cell: &Data) -> String {
     let value = match cell {
         Data::Int(value) => {value.to_string()},
         Data::Float(value) => {value.to_string()},
         Data::String(value) => {value.to_string()},
         Data::Bool(value) => {value.to_string()},
-        Data::DateTime(value) => {value.to_string()},
-        Data::DateTimeIso(value) => {value.to_string()},
-        Data::DurationIso(value) => {value.to_string()},
+        Data::DateTime(value) => {excel_moment_text(value)},
+        Data::DateTimeIso(value) => {value.replacen('T', " ", 1)},
+        Data::DurationIso(value) => {
+            match iso_duration_seconds(value) {
+                Some(seconds) if seconds < DAY => time_text(seconds),
+                _ => value.to_string(),
+            }
+        },
         Data::Error(value) => {value.to_string()},
         Data::Empty => {"".to_string()},
     };
@@ -155,13 +280,79 @@ fn data_to_string(cell: &Data) -> String {
 fn db_type(cell: &Data) -> &'static str {
     match cell {
         Data::Int(_) => "BIGINT",
-        Data::Float(_) => "FLOAT",
+        Data::Float(_) => "DOUBLE",
+        Data::String(value) if value.trim().is_empty() => "",
         Data::String(_) => "TEXT",
         Data::Bool(_) => "BOOLEAN",
-        Data::DateTime(_) => "TEXT",
-        Data::DateTimeIso(_) => "TEXT",
-        Data::DurationIso(_) => "TEXT",
+        Data::DateTime(value) => excel_moment_type(value),
+        Data::DateTimeIso(value) if value.contains('T') => "TIMESTAMP",
+        Data::DateTimeIso(_) => "DATE",
+        Data::DurationIso(value) => match iso_duration_seconds(value) {
+            Some(seconds) if seconds < DAY => "TIME",
+            _ => "TEXT",
+        },
         Data::Error(_) => "TEXT",
         Data::Empty => ""
     }
-}
\ No newline at end of file
+}
+
+const DAY: f64 = 86400.0;
+
+/// calamine marks a cell as a date or time from its number format, but every
+/// one holds a count of days: a time of day is under one, a date is whole,
+/// and a timestamp is both. A duration longer than a day is not a TIME, so
+/// it stays as text.
+fn excel_moment_type(value: &ExcelDateTime) -> &'static str {
+    let days = value.as_f64();
+    if days < 1.0 {
+        "TIME"
+    } else if value.is_duration() {
+        "TEXT"
+    } else if days.fract() == 0.0 {
+        "DATE"
+    } else {
+        "TIMESTAMP"
+    }
+}
+
+fn excel_moment_text(value: &ExcelDateTime) -> String {
+    let days = value.as_f64();
+    let moment = match excel_moment_type(value) {
+        "TIME" => return time_text(days * DAY),
+        "DATE" => value.as_datetime().map(|moment| moment.format("%Y-%m-%d").to_string()),
+        "TIMESTAMP" => value.as_datetime().map(|moment| moment.format("%Y-%m-%d %H:%M:%S%.f").to_string()),
+        _ => None,
+    };
+    moment.unwrap_or_else(|| days.to_string())
+}
+
+/// Seconds into a day as HH:MM:SS, with milliseconds when there are any.
+fn time_text(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    let (whole, millis) = (millis / 1000, millis % 1000);
+    let text = format!("{:02}:{:02}:{:02}", whole / 3600, whole / 60 % 60, whole % 60);
+    if millis == 0 {
+        text
+    } else {
+        format!("{}.{:03}", text, millis)
+    }
+}
+
+/// The length of an .ods time, which calamine passes on as an ISO 8601
+/// duration like PT10H30M00S.
+fn iso_duration_seconds(value: &str) -> Option<f64> {
+    let mut rest = value.strip_prefix("PT")?;
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let unit_at = rest.find(|c: char| c.is_ascii_alphabetic())?;
+        let amount: f64 = rest[..unit_at].parse().ok()?;
+        seconds += amount * match &rest[unit_at..unit_at + 1] {
+            "H" => 3600.0,
+            "M" => 60.0,
+            "S" => 1.0,
+            _ => return None,
+        };
+        rest = &rest[unit_at + 1..];
+    }
+    Some(seconds)
+}
@@ -0,0 +1,296 @@
+//! Geometry values as text.
+//!
+//! duckdb hands a GEOMETRY cell back as WKB, which is bytes rather than
+//! anything a person can read. Every route out — the grid, the exports and the
+//! command line — writes it as WKT instead, or as GeoJSON when the run asks for
+//! that (`--geometry-format geojson`), which is what a map library takes. The
+//! parsing is done here rather than by wrapping queries in `ST_AsText`, so a
+//! cell renders the same however it was selected, and without the spatial
+//! extension loaded.
+
+/// How a geometry cell is written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GeometryFormat {
+    /// `POINT (1 2)`: well-known text, as `ST_AsText` writes it.
+    #[default]
+    Wkt,
+    /// `{"type": "Point", "coordinates": [1.0, 2.0]}`. M values are left out,
+    /// since GeoJSON has no place for them.
+    #[value(name = "geojson")]
+    GeoJson,
+}
+
+/// A WKB value as text in `format`. Something that does not parse as WKB is
+/// written as hex, which is at least readable and reversible.
+pub fn render(wkb: &[u8], format: GeometryFormat) -> String {
+    let mut reader = Reader { bytes: wkb, at: 0 };
+    match read_geometry(&mut reader) {
+        Some(geometry) => match format {
+            GeometryFormat::Wkt => wkt(&geometry),
+            GeometryFormat::GeoJson => geojson(&geometry).to_string(),
+        },
+        None => wkb.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+const NAMES: [&str; 7] = [
+    "POINT",
+    "LINESTRING",
+    "POLYGON",
+    "MULTIPOINT",
+    "MULTILINESTRING",
+    "MULTIPOLYGON",
+    "GEOMETRYCOLLECTION",
+];
+
+struct Geometry {
+    /// The WKB type code without its dimensions: 1 for a point up to 7 for a
+    /// collection, which is also its place in [`NAMES`] plus one.
+    kind: u32,
+    z: bool,
+    m: bool,
+    shape: Shape,
+}
+
+enum Shape {
+    /// An empty point is all NaN.
+    Point(Vec<f64>),
+    Line(Vec<Vec<f64>>),
+    Rings(Vec<Vec<Vec<f64>>>),
+    /// The members of a multi-geometry or a collection, each a geometry of
+    /// its own in WKB.
+    Parts(Vec<Geometry>),
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let taken = self.bytes.get(self.at..self.at + N)?.try_into().ok()?;
+        self.at += N;
+        Some(taken)
+    }
+
+    fn u32(&mut self, little: bool) -> Option<u32> {
+        let bytes = self.take::<4>()?;
+        Some(if little { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    fn f64(&mut self, little: bool) -> Option<f64> {
+        let bytes = self.take::<8>()?;
+        Some(if little { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+    }
+
+    /// A count of what follows. Each of those is at least a byte, so a count
+    /// larger than what is left is a broken value, refused before anything is
+    /// allocated for it.
+    fn count(&mut self, little: bool) -> Option<usize> {
+        let count = self.u32(little)? as usize;
+        (count <= self.bytes.len() - self.at).then_some(count)
+    }
+
+    fn point(&mut self, little: bool, width: usize) -> Option<Vec<f64>> {
+        (0..width).map(|_| self.f64(little)).collect()
+    }
+
+    fn points(&mut self, little: bool, width: usize) -> Option<Vec<Vec<f64>>> {
+        let count = self.count(little)?;
+        (0..count).map(|_| self.point(little, width)).collect()
+    }
+}
+
+/// One geometry, in ISO WKB or PostGIS's extended WKB: the dimensions are
+/// either added to the type code in thousands or set as its high bits, and an
+/// extended one may carry an SRID, which is skipped.
+fn read_geometry(reader: &mut Reader) -> Option<Geometry> {
+    let little = match reader.take::<1>()?[0] {
+        0 => false,
+        1 => true,
+        _ => return None,
+    };
+    let code = reader.u32(little)?;
+    let mut z = code & 0x8000_0000 != 0;
+    let mut m = code & 0x4000_0000 != 0;
+    if code & 0x2000_0000 != 0 {
+        reader.u32(little)?;
+    }
+    let iso = code & 0x0fff_ffff;
+    match iso / 1000 {
+        0 => {}
+        1 => z = true,
+        2 => m = true,
+        3 => (z, m) = (true, true),
+        _ => return None,
+    }
+    let kind = iso % 1000;
+    let width = 2 + z as usize + m as usize;
+    let shape = match kind {
+        1 => Shape::Point(reader.point(little, width)?),
+        2 => Shape::Line(reader.points(little, width)?),
+        3 => {
+            let rings = reader.count(little)?;
+            Shape::Rings((0..rings).map(|_| reader.points(little, width)).collect::<Option<_>>()?)
+        }
+        4..=7 => {
+            let parts = reader.count(little)?;
+            Shape::Parts((0..parts).map(|_| read_geometry(reader)).collect::<Option<_>>()?)
+        }
+        _ => return None,
+    };
+    Some(Geometry { kind, z, m, shape })
+}
+
+fn wkt(geometry: &Geometry) -> String {
+    let dimensions = match (geometry.z, geometry.m) {
+        (false, false) => "",
+        (true, false) => " Z",
+        (false, true) => " M",
+        (true, true) => " ZM",
+    };
+    let name = NAMES[geometry.kind as usize - 1];
+    format!(
+        "{}{} {}",
+        name,
+        dimensions,
+        wkt_body(geometry).unwrap_or_else(|| "EMPTY".to_string())
+    )
+}
+
+/// What follows the name: the parenthesised coordinates, or `None` for an
+/// empty geometry. The members of a multi-geometry are written without their
+/// names, and a collection's with them.
+fn wkt_body(geometry: &Geometry) -> Option<String> {
+    let position = |point: &Vec<f64>| {
+        point.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ")
+    };
+    let sequence = |points: &Vec<Vec<f64>>| {
+        format!("({})", points.iter().map(position).collect::<Vec<_>>().join(", "))
+    };
+    match &geometry.shape {
+        Shape::Point(point) if point.iter().all(|value| value.is_nan()) => None,
+        Shape::Point(point) => Some(format!("({})", position(point))),
+        Shape::Line(points) if points.is_empty() => None,
+        Shape::Line(points) => Some(sequence(points)),
+        Shape::Rings(rings) if rings.is_empty() => None,
+        Shape::Rings(rings) => {
+            Some(format!("({})", rings.iter().map(sequence).collect::<Vec<_>>().join(", ")))
+        }
+        Shape::Parts(parts) if parts.is_empty() => None,
+        Shape::Parts(parts) => {
+            let members: Vec<String> = parts
+                .iter()
+                .map(|part| match geometry.kind {
+                    7 => wkt(part),
+                    _ => wkt_body(part).unwrap_or_else(|| "EMPTY".to_string()),
+                })
+                .collect();
+            Some(format!("({})", members.join(", ")))
+        }
+    }
+}
+
+fn geojson(geometry: &Geometry) -> serde_json::Value {
+    let kind = match geometry.kind {
+        1 => "Point",
+        2 => "LineString",
+        3 => "Polygon",
+        4 => "MultiPoint",
+        5 => "MultiLineString",
+        6 => "MultiPolygon",
+        _ => "GeometryCollection",
+    };
+    match &geometry.shape {
+        Shape::Parts(parts) if geometry.kind == 7 => serde_json::json!({
+            "type": kind,
+            "geometries": parts.iter().map(geojson).collect::<Vec<_>>(),
+        }),
+        _ => serde_json::json!({ "type": kind, "coordinates": coordinates(geometry) }),
+    }
+}
+
+fn coordinates(geometry: &Geometry) -> serde_json::Value {
+    // x, y and z when there is one; m has nowhere to go
+    let width = if geometry.z { 3 } else { 2 };
+    let position = |point: &Vec<f64>| serde_json::json!(point[..width]);
+    match &geometry.shape {
+        Shape::Point(point) if point.iter().all(|value| value.is_nan()) => serde_json::json!([]),
+        Shape::Point(point) => position(point),
+        Shape::Line(points) => serde_json::json!(points.iter().map(position).collect::<Vec<_>>()),
+        Shape::Rings(rings) => serde_json::json!(rings
+            .iter()
+            .map(|ring| ring.iter().map(position).collect::<Vec<_>>())
+            .collect::<Vec<_>>()),
+        Shape::Parts(parts) => serde_json::json!(parts.iter().map(coordinates).collect::<Vec<_>>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Vec<u8> {
+        let mut wkb = vec![1];
+        wkb.extend(1u32.to_le_bytes());
+        wkb.extend(x.to_le_bytes());
+        wkb.extend(y.to_le_bytes());
+        wkb
+    }
+
+    #[test]
+    fn wkb_is_written_as_wkt_or_geojson() {
+        assert_eq!(render(&point(1.0, 2.5), GeometryFormat::Wkt), "POINT (1 2.5)");
+        // compared as json: the key order is serde_json's to choose
+        let geojson = |wkb: &[u8]| -> serde_json::Value {
+            serde_json::from_str(&render(wkb, GeometryFormat::GeoJson)).unwrap()
+        };
+        assert_eq!(geojson(&point(1.0, 2.5)), serde_json::json!({"type": "Point", "coordinates": [1.0, 2.5]}));
+        assert_eq!(render(&point(f64::NAN, f64::NAN), GeometryFormat::Wkt), "POINT EMPTY");
+
+        // a big-endian polygon with one ring
+        let mut polygon = vec![0];
+        polygon.extend(3u32.to_be_bytes());
+        polygon.extend(1u32.to_be_bytes());
+        polygon.extend(4u32.to_be_bytes());
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            polygon.extend(f64::to_be_bytes(x));
+            polygon.extend(f64::to_be_bytes(y));
+        }
+        assert_eq!(render(&polygon, GeometryFormat::Wkt), "POLYGON ((0 0, 1 0, 1 1, 0 0))");
+
+        // a multipoint's members are whole geometries, written without names
+        let mut multipoint = vec![1];
+        multipoint.extend(4u32.to_le_bytes());
+        multipoint.extend(2u32.to_le_bytes());
+        multipoint.extend(point(1.0, 2.0));
+        multipoint.extend(point(3.0, 4.0));
+        assert_eq!(render(&multipoint, GeometryFormat::Wkt), "MULTIPOINT ((1 2), (3 4))");
+        assert_eq!(
+            geojson(&multipoint),
+            serde_json::json!({"type": "MultiPoint", "coordinates": [[1.0, 2.0], [3.0, 4.0]]})
+        );
+
+        // ISO's thousands say Z, and the extended form's high bit does too
+        let mut iso = vec![1];
+        iso.extend(1001u32.to_le_bytes());
+        for value in [1.0f64, 2.0, 3.0] {
+            iso.extend(value.to_le_bytes());
+        }
+        assert_eq!(render(&iso, GeometryFormat::Wkt), "POINT Z (1 2 3)");
+        let mut extended = vec![1];
+        extended.extend((0x8000_0000u32 | 0x2000_0000 | 1).to_le_bytes());
+        extended.extend(4326u32.to_le_bytes());
+        for value in [1.0f64, 2.0, 3.0] {
+            extended.extend(value.to_le_bytes());
+        }
+        assert_eq!(render(&extended, GeometryFormat::Wkt), "POINT Z (1 2 3)");
+
+        // what is not WKB stays as it was
+        assert_eq!(render(&[7, 1, 2], GeometryFormat::Wkt), "070102");
+        let mut truncated = point(1.0, 2.0);
+        truncated.pop();
+        assert_eq!(render(&truncated, GeometryFormat::Wkt).len(), truncated.len() * 2);
+    }
+}
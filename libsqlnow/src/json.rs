@@ -1,6 +1,9 @@
 use duckdb::Connection;
 use eyre::Result;
 use libflatterer::{flatten_all, guess_array, Options};
+
+use crate::session::quote_literal;
+use crate::{loaded_comment, InputOptions};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, Read};
@@ -43,7 +46,34 @@ fn to_duckdb_type(json_type: &str) -> String {
     }
 }
 
-pub fn load_json(file: &str, name: &str, selected_tables: &[String], drop: bool, connection: &Connection) -> Result<()> {
+/// libflatterer's options for one input. Its builder sets each field once, so
+/// what the input leaves unset keeps the builder's default and the rest are
+/// written over the built options.
+fn flatten_options(main_table_name: &str, stream: bool, input_options: &InputOptions) -> Options {
+    let mut options = Options::builder().main_table_name(main_table_name.to_owned()).json_stream(stream).force(true).build();
+    if let Some(separator) = &input_options.separator {
+        options.path_separator = separator.clone();
+    }
+    if let Some(inline_one_to_one) = input_options.inline_one_to_one {
+        options.inline_one_to_one = inline_one_to_one;
+    }
+    if let Some(links) = input_options.links {
+        options.no_link = !links;
+    }
+    if let Some(id_prefix) = &input_options.id_prefix {
+        options.id_prefix = id_prefix.clone();
+    }
+    if let Some(path) = &input_options.path {
+        options.path = path.split('/').map(str::to_string).collect();
+    }
+    options
+}
+
+/// Flatten a json file into tables: the top-level objects into one named
+/// after the input, and every nested list of objects into a table of its own,
+/// joined to its parent by `_link` columns. The input's flattener options
+/// shape that split; see [`flatten_options`].
+pub fn load_json(file: &str, name: &str, selected_tables: &[String], input_options: &InputOptions, drop: bool, connection: &Connection) -> Result<()> {
 
     let start = read_first_bytes(file)?;
     let (file_type, _) = guess_array(&start)?;
@@ -58,7 +88,7 @@ pub fn load_json(file: &str, name: &str, selected_tables: &[String], drop: bool,
         file_stem
     };
 
-    let options = Options::builder().main_table_name(schema_or_table.to_owned()).json_stream(stream).force(true).build();
+    let options = flatten_options(schema_or_table, stream, input_options);
 
     let temp_dir = TempDir::new()?;
 
@@ -108,6 +138,9 @@ pub fn load_json(file: &str, name: &str, selected_tables: &[String], drop: bool,
 
         let create_table = format!("CREATE TABLE {} ({})", db_table_name, columns.join(", "));
         connection.execute(&create_table, [])?;
+        if !single_table {
+            connection.execute_batch(&format!("COMMENT ON TABLE {} IS {};", db_table_name, quote_literal(&loaded_comment(schema_or_table))))?;
+        }
 
         let mut csv_path = temp_dir.path().join("csv").join(format!("{}.csv", table_name)).to_string_lossy().to_string();
         csv_path = csv_path.replace("'", "''");
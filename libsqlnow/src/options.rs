@@ -0,0 +1,548 @@
+//! Per-input reader options: how one file is read, where duckdb's own
+//! detection is not enough.
+//!
+//! They travel with the input everywhere it goes — given as `--with key=value`
+//! after it on the command line or as an `options` object to
+//! `POST /api/inputs`, and stored as JSON beside it in the session `inputs`
+//! table — so a resumed session, and `sqlnow sql` replaying the session's
+//! views, read the file exactly as the first run did.
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::session::quote_literal;
+use crate::{DbType, FileFormat};
+
+/// How to read one input. Every field is optional, and unset ones are left to
+/// duckdb: an input with no options reads exactly as it did before they
+/// existed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputOptions {
+    /// The column separator, which duckdb otherwise guesses.
+    #[serde(default, alias = "sep", alias = "delimiter", skip_serializing_if = "Option::is_none")]
+    pub delim: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape: Option<String>,
+    /// Whether the first row names the columns. On by default; off, a CSV's
+    /// columns are called column0, column1 and so on, and a spreadsheet's
+    /// column_1, column_2.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<bool>,
+    /// Lines to skip before the header (or the data, with no header).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip: Option<u64>,
+    /// strftime formats for the dates and timestamps in the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dateformat: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestampformat: Option<String>,
+    /// utf-8 (the default), utf-16 or latin-1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Column name to duckdb type, for the columns whose guessed type is
+    /// wrong, in a CSV or a spreadsheet. The rest are still detected.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub types: BTreeMap<String, String>,
+    /// Load the lines that parse and set aside the ones that do not, in a
+    /// `<name>_rejects` table, rather than failing the whole file on one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejects: Option<bool>,
+    /// For a spreadsheet: the row (numbered as in the sheet, from 1) that
+    /// names the columns, when title rows come before it. Rows above it are
+    /// left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_row: Option<u32>,
+    /// For a spreadsheet: the cells to read, in A1 notation — `B3:K200`, or
+    /// `B3` for everything below and right of it. The header is the first
+    /// row of the range unless `header_row` says otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    /// For json loaded as tables: what joins the keys on the way to a nested
+    /// list into its table's name, `_` unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    /// For json loaded as tables: fold a nested object that is only ever one
+    /// per parent into the parent's columns, rather than a table of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_one_to_one: Option<bool>,
+    /// For json loaded as tables: whether child tables get the `_link`
+    /// columns joining them to their parents. On unless set to false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<bool>,
+    /// For json loaded as tables: put before every id in the `_link`
+    /// columns, so the ids of two loads cannot be confused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_prefix: Option<String>,
+    /// For json loaded as tables: the keys down to the list to load,
+    /// `/`-separated — `data` for an API's `{"data": [...]}` — rather than
+    /// the whole document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// For json loaded as tables: flattened into relational tables (the
+    /// default), or copied whole as duckdb reads it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<JsonMode>,
+    /// For a spatial file: the layer to read, by name, where there is more
+    /// than one — a GeoPackage usually has several. The first unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    /// For a Delta table: the version to read, rather than the latest —
+    /// time travel, pinned for as long as the input is attached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// For an Iceberg table: the id of the snapshot to read, rather than the
+    /// current one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<u64>,
+    /// For an attached database: the schemas to show, rather than all of
+    /// them. A Postgres database given one is attached with that schema
+    /// alone, so a warehouse's hundreds of others are never even listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemas: Vec<String>,
+    /// Read every column as text (true) or typed (false). For a CSV file
+    /// that is `all_varchar`, typed unless `--text` was given; SQLite is read
+    /// as text unless this says false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<bool>,
+}
+
+/// How a json file loaded as a table is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonMode {
+    /// Split by libflatterer into a table per nested list, joined by `_link`
+    /// columns, by way of CSV. Every column is a plain scalar.
+    Flatten,
+    /// One table straight from duckdb's `read_json`, typed as it reads and
+    /// with nested objects and lists kept as STRUCT and LIST columns. Much
+    /// quicker on a big JSONL file, which never goes through CSV.
+    Native,
+}
+
+impl InputOptions {
+    pub fn is_empty(&self) -> bool {
+        self == &InputOptions::default()
+    }
+
+    /// Apply one `key=value` from the command line. Booleans take
+    /// true/false (or yes/no, 1/0), and `types` takes a comma-separated
+    /// `column:TYPE` list, commas inside a type's parentheses included:
+    /// `types=id:BIGINT,amount:DECIMAL(10,2)`.
+    pub fn set_assignment(&mut self, assignment: &str) -> Result<()> {
+        let (key, value) = assignment.split_once('=').ok_or_else(|| {
+            eyre::eyre!("\"{}\" is not key=value", assignment)
+        })?;
+        let text = || Some(value.to_string());
+        match key.trim() {
+            "delim" | "sep" | "delimiter" => self.delim = text(),
+            "quote" => self.quote = text(),
+            "escape" => self.escape = text(),
+            "header" => self.header = Some(parse_bool(key, value)?),
+            "skip" => {
+                self.skip = Some(value.trim().parse().map_err(|_| {
+                    eyre::eyre!("skip takes a number of lines, not \"{}\"", value)
+                })?)
+            }
+            "dateformat" => self.dateformat = text(),
+            "timestampformat" => self.timestampformat = text(),
+            "encoding" => self.encoding = text(),
+            "rejects" => self.rejects = Some(parse_bool(key, value)?),
+            "text" => self.text = Some(parse_bool(key, value)?),
+            "header_row" => {
+                self.header_row = Some(value.trim().parse().ok().filter(|row| *row > 0).ok_or_else(|| {
+                    eyre::eyre!("header_row takes a row number from 1, not \"{}\"", value)
+                })?)
+            }
+            "range" => self.range = Some(value.trim().to_string()),
+            "separator" => self.separator = text(),
+            "inline_one_to_one" => self.inline_one_to_one = Some(parse_bool(key, value)?),
+            "links" => self.links = Some(parse_bool(key, value)?),
+            "id_prefix" => self.id_prefix = text(),
+            "path" => self.path = Some(value.trim().trim_matches('/').to_string()),
+            "json_mode" => {
+                self.json_mode = Some(match value.trim() {
+                    "flatten" => JsonMode::Flatten,
+                    "native" => JsonMode::Native,
+                    _ => return Err(eyre::eyre!("json_mode is flatten or native, not \"{}\"", value)),
+                })
+            }
+            "layer" => self.layer = Some(value.trim().to_string()),
+            "version" => {
+                self.version = Some(value.trim().parse().map_err(|_| {
+                    eyre::eyre!("version takes a Delta table version number, not \"{}\"", value)
+                })?)
+            }
+            "snapshot" => {
+                self.snapshot = Some(value.trim().parse().map_err(|_| {
+                    eyre::eyre!("snapshot takes an Iceberg snapshot id, not \"{}\"", value)
+                })?)
+            }
+            "schemas" => {
+                self.schemas = value
+                    .split(',')
+                    .map(|schema| schema.trim().to_string())
+                    .filter(|schema| !schema.is_empty())
+                    .collect()
+            }
+            "types" => {
+                for pair in split_outside_parentheses(value) {
+                    let (column, column_type) = pair.split_once(':').ok_or_else(|| {
+                        eyre::eyre!("types takes column:TYPE pairs, not \"{}\"", pair)
+                    })?;
+                    check_type(column_type.trim())?;
+                    self.types.insert(column.trim().to_string(), column_type.trim().to_string());
+                }
+            }
+            other => {
+                return Err(eyre::eyre!(
+                    "unknown input option \"{}\" (known: {})",
+                    other,
+                    KNOWN.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the options that are set, in the order they are listed.
+    pub fn keys(&self) -> Vec<&'static str> {
+        let set = [
+            self.delim.is_some(),
+            self.quote.is_some(),
+            self.escape.is_some(),
+            self.header.is_some(),
+            self.skip.is_some(),
+            self.dateformat.is_some(),
+            self.timestampformat.is_some(),
+            self.encoding.is_some(),
+            !self.types.is_empty(),
+            self.rejects.is_some(),
+            self.header_row.is_some(),
+            self.range.is_some(),
+            self.separator.is_some(),
+            self.inline_one_to_one.is_some(),
+            self.links.is_some(),
+            self.id_prefix.is_some(),
+            self.path.is_some(),
+            self.json_mode.is_some(),
+            self.layer.is_some(),
+            self.version.is_some(),
+            self.snapshot.is_some(),
+            !self.schemas.is_empty(),
+            self.text.is_some(),
+        ];
+        KNOWN.iter().zip(set).filter(|(_, set)| *set).map(|(key, _)| *key).collect()
+    }
+
+    /// The options that are set but that `format`'s reader does not take.
+    /// They are refused rather than ignored, since a file read without them
+    /// reads differently from what was asked.
+    pub fn keys_not_for(&self, format: Option<FileFormat>) -> Vec<&'static str> {
+        let takes: &[&str] = match format {
+            Some(FileFormat::Csv) => CSV,
+            Some(FileFormat::Workbook) => SPREADSHEET,
+            Some(FileFormat::Json) => JSON,
+            Some(FileFormat::Spatial) => SPATIAL,
+            Some(FileFormat::Delta) => &["version"],
+            Some(FileFormat::Iceberg) => &["snapshot"],
+            _ => &[],
+        };
+        self.keys().into_iter().filter(|key| !takes.contains(key)).collect()
+    }
+
+    /// The options that are set but that an attached database of `db_type`
+    /// does not take: any can keep to some of its schemas, and SQLite can be
+    /// read typed.
+    pub fn keys_not_for_database(&self, db_type: DbType) -> Vec<&'static str> {
+        let takes: &[&str] = match db_type {
+            DbType::Sqlite => &["schemas", "text"],
+            _ => &["schemas"],
+        };
+        self.keys().into_iter().filter(|key| !takes.contains(key)).collect()
+    }
+
+    /// The flattener's options that are set, which only mean something to
+    /// json loaded as tables.
+    pub fn flattener_keys(&self) -> Vec<&'static str> {
+        self.keys().into_iter().filter(|key| FLATTENER.contains(key)).collect()
+    }
+
+    /// The arguments to `read_csv` after the file itself, header included.
+    pub fn csv_arguments(&self) -> String {
+        let mut arguments = vec![format!("header = {}", self.header.unwrap_or(true))];
+        for (name, value) in [
+            ("delim", &self.delim),
+            ("quote", &self.quote),
+            ("escape", &self.escape),
+            ("dateformat", &self.dateformat),
+            ("timestampformat", &self.timestampformat),
+            ("encoding", &self.encoding),
+        ] {
+            if let Some(value) = value {
+                arguments.push(format!("{} = {}", name, quote_literal(value)));
+            }
+        }
+        if let Some(skip) = self.skip {
+            arguments.push(format!("skip = {}", skip));
+        }
+        if !self.types.is_empty() {
+            let types: Vec<String> = self
+                .types
+                .iter()
+                .map(|(column, column_type)| {
+                    format!("{}: {}", quote_literal(column), quote_literal(column_type))
+                })
+                .collect();
+            arguments.push(format!("types = {{{}}}", types.join(", ")));
+        }
+        if self.tolerant() {
+            arguments.push("ignore_errors = true".to_string());
+        }
+        arguments.join(", ")
+    }
+
+    /// Whether lines that do not parse are set aside rather than fatal.
+    pub fn tolerant(&self) -> bool {
+        self.rejects == Some(true)
+    }
+
+    /// As stored in the session: `NULL` rather than `{}` when there are none,
+    /// so sessions written before options existed look like every other one.
+    pub fn to_stored(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(serde_json::to_string(self).expect("options serialize"))
+    }
+
+    /// Read back what [`InputOptions::to_stored`] wrote. Something this build
+    /// cannot read — options from a newer sqlnow — is an error rather than
+    /// silently dropped, since a file read without them reads differently.
+    pub fn from_stored(stored: Option<&str>) -> Result<InputOptions> {
+        match stored {
+            None => Ok(InputOptions::default()),
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| eyre::eyre!("unreadable input options {}: {}", json, e)),
+        }
+    }
+}
+
+/// Refuse a `types` value that is anything but a single duckdb type. A
+/// spreadsheet's table is made with it written into the CREATE TABLE as it
+/// is, so it is checked twice over: its characters are those of a type name —
+/// no quote, semicolon or comment can start, and its parentheses balance so
+/// nothing closes the column early — and duckdb will prepare a cast to it,
+/// so it is a type duckdb knows.
+pub fn check_type(column_type: &str) -> Result<()> {
+    let refused = |why: &str| eyre::eyre!("types takes duckdb types, and \"{}\" is not one{}", column_type, why);
+    let mut depth = 0;
+    for c in column_type.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(refused("")),
+            ')' => depth -= 1,
+            c if c.is_alphanumeric() || " _,[]".contains(c) => {}
+            _ => return Err(refused("")),
+        }
+    }
+    if depth != 0 || column_type.trim().is_empty() {
+        return Err(refused(""));
+    }
+    let connection = duckdb::Connection::open_in_memory()?;
+    connection
+        .prepare(&format!("SELECT CAST(NULL AS {})", column_type))
+        .map_err(|e| refused(&format!(": {}", e)))?;
+    Ok(())
+}
+
+const KNOWN: &[&str] = &[
+    "delim",
+    "quote",
+    "escape",
+    "header",
+    "skip",
+    "dateformat",
+    "timestampformat",
+    "encoding",
+    "types",
+    "rejects",
+    "header_row",
+    "range",
+    "separator",
+    "inline_one_to_one",
+    "links",
+    "id_prefix",
+    "path",
+    "json_mode",
+    "layer",
+    "version",
+    "snapshot",
+    "schemas",
+    "text",
+];
+
+/// The options `read_csv` takes.
+const CSV: &[&str] = &[
+    "delim",
+    "quote",
+    "escape",
+    "header",
+    "skip",
+    "dateformat",
+    "timestampformat",
+    "encoding",
+    "types",
+    "rejects",
+    "text",
+];
+
+const SPREADSHEET: &[&str] = &["header", "header_row", "range", "types"];
+
+/// The options for json loaded as tables.
+const JSON: &[&str] = &["separator", "inline_one_to_one", "links", "id_prefix", "path", "json_mode"];
+
+/// What `ST_Read` takes.
+const SPATIAL: &[&str] = &["layer"];
+
+/// Those of them that only the flattener takes.
+const FLATTENER: &[&str] = &["separator", "inline_one_to_one", "links", "id_prefix", "path"];
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(eyre::eyre!("{} takes true or false, not \"{}\"", key, value)),
+    }
+}
+
+fn split_outside_parentheses(value: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts.into_iter().filter(|part| !part.trim().is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_line_options_become_read_csv_arguments() {
+        let mut options = InputOptions::default();
+        for assignment in [
+            "delim=;",
+            "header=no",
+            "skip=2",
+            "dateformat=%d/%m/%Y",
+            "types=id:BIGINT, amount:DECIMAL(10,2)",
+        ] {
+            options.set_assignment(assignment).unwrap();
+        }
+        assert_eq!(
+            options.csv_arguments(),
+            "header = false, delim = ';', dateformat = '%d/%m/%Y', skip = 2, \
+             types = {'amount': 'DECIMAL(10,2)', 'id': 'BIGINT'}"
+        );
+        assert_eq!(options.keys(), ["delim", "header", "skip", "dateformat", "types"]);
+
+        // what is stored reads back the same, and nothing stored is no options
+        let stored = options.to_stored().unwrap();
+        assert_eq!(InputOptions::from_stored(Some(&stored)).unwrap(), options);
+        assert_eq!(InputOptions::default().to_stored(), None);
+        assert!(InputOptions::from_stored(None).unwrap().is_empty());
+
+        // a typo is refused with the options that do exist
+        let complaint = options.set_assignment("delimter=,").unwrap_err().to_string();
+        assert!(complaint.contains("known: delim"), "{}", complaint);
+        assert!(options.set_assignment("header=maybe").is_err());
+        assert!(InputOptions::from_stored(Some(r#"{"delimter": ","}"#)).is_err());
+
+        // each reader takes its own options, and only those
+        assert!(options.keys_not_for(Some(FileFormat::Csv)).is_empty());
+        assert_eq!(
+            options.keys_not_for(Some(FileFormat::Workbook)),
+            ["delim", "skip", "dateformat"]
+        );
+        let mut sheet = InputOptions::default();
+        sheet.set_assignment("header_row=3").unwrap();
+        sheet.set_assignment("range=B3:K200").unwrap();
+        assert!(sheet.keys_not_for(Some(FileFormat::Workbook)).is_empty());
+        assert_eq!(sheet.keys_not_for(Some(FileFormat::Csv)), ["header_row", "range"]);
+        assert!(sheet.set_assignment("header_row=0").is_err());
+
+        let mut nested = InputOptions::default();
+        nested.set_assignment("path=/data/items/").unwrap();
+        nested.set_assignment("links=false").unwrap();
+        assert_eq!(nested.path.as_deref(), Some("data/items"));
+        assert_eq!(nested.flattener_keys(), ["links", "path"]);
+        assert!(sheet.flattener_keys().is_empty());
+        assert!(nested.keys_not_for(Some(FileFormat::Json)).is_empty());
+        assert_eq!(nested.keys_not_for(Some(FileFormat::Csv)), ["links", "path"]);
+
+        nested.set_assignment("json_mode=native").unwrap();
+        assert_eq!(nested.json_mode, Some(JsonMode::Native));
+        assert_eq!(nested.flattener_keys(), ["links", "path"]);
+        assert!(nested.set_assignment("json_mode=nested").is_err());
+
+        let mut layered = InputOptions::default();
+        layered.set_assignment("layer= parcels ").unwrap();
+        assert_eq!(layered.layer.as_deref(), Some("parcels"));
+        assert!(layered.keys_not_for(Some(FileFormat::Spatial)).is_empty());
+        assert_eq!(layered.keys_not_for(Some(FileFormat::Csv)), ["layer"]);
+        assert_eq!(nested.keys_not_for(Some(FileFormat::Spatial)), ["links", "path", "json_mode"]);
+
+        let mut pinned = InputOptions::default();
+        pinned.set_assignment("version=3").unwrap();
+        assert!(pinned.keys_not_for(Some(FileFormat::Delta)).is_empty());
+        assert_eq!(pinned.keys_not_for(Some(FileFormat::Iceberg)), ["version"]);
+        assert!(pinned.set_assignment("version=latest").is_err());
+        pinned.set_assignment("snapshot=4182760285219470000").unwrap();
+        assert_eq!(pinned.keys_not_for(Some(FileFormat::Delta)), ["snapshot"]);
+
+        let mut warehouse = InputOptions::default();
+        warehouse.set_assignment("schemas=public, sales,").unwrap();
+        assert_eq!(warehouse.schemas, ["public", "sales"]);
+        assert!(warehouse.keys_not_for_database(DbType::Postgres).is_empty());
+        assert_eq!(warehouse.keys_not_for(Some(FileFormat::Csv)), ["schemas"]);
+        assert_eq!(pinned.keys_not_for_database(DbType::DuckDb), ["version", "snapshot"]);
+
+        let mut typed = InputOptions::default();
+        typed.set_assignment("text=false").unwrap();
+        assert_eq!(typed.text, Some(false));
+        assert!(typed.keys_not_for_database(DbType::Sqlite).is_empty());
+        assert_eq!(typed.keys_not_for_database(DbType::Postgres), ["text"]);
+        assert!(typed.keys_not_for(Some(FileFormat::Csv)).is_empty());
+
+        // a type goes into a spreadsheet table's DDL, so it is one type or
+        // nothing
+        let mut cast = InputOptions::default();
+        cast.set_assignment("types=tags:VARCHAR[], site:STRUCT(country VARCHAR, mw INTEGER)").unwrap();
+        assert_eq!(cast.types["site"], "STRUCT(country VARCHAR, mw INTEGER)");
+        for refused in [
+            "types=id:PLANT",
+            "types=id:BIGINT); DROP TABLE sales; --",
+            "types=id:BIGINT) AS id, (SELECT 1",
+            "types=id:BIGINT NULL, extra BIGINT",
+            "types=id:",
+        ] {
+            assert!(cast.set_assignment(refused).is_err(), "{}", refused);
+        }
+        assert!(!cast.types.contains_key("id"));
+    }
+}
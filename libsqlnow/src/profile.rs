@@ -0,0 +1,172 @@
+//! Column profiles: what is in each column of a table or a result, without
+//! writing the SQL to find out.
+//!
+//! duckdb's `SUMMARIZE` does most of it in one scan — the minimum and maximum,
+//! an approximate distinct count, the share of nulls and the quartiles. It does
+//! not keep the most frequent values, so those are one more query, grouping
+//! every column at once. Two scans in all, however wide: the relation may be a
+//! whole query over a remote database, run again for each.
+
+use crate::session::quote_ident;
+use duckdb::Connection;
+use eyre::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How many of a column's most frequent values a profile lists.
+const TOP_VALUES: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    /// The rows in the table or result, all of them rather than a page.
+    pub rows: u64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    /// As text, whatever the column's type, which is how `SUMMARIZE` gives
+    /// them; `None` for a column that is all nulls.
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// A HyperLogLog estimate, so it can be a few percent out either way.
+    pub approx_distinct: Option<u64>,
+    pub null_percentage: Option<f64>,
+    /// The 25th, 50th and 75th percentiles, for a column that has them:
+    /// numbers and dates, not text.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quantiles: Vec<String>,
+    /// The most frequent values that are not null, most frequent first. Empty
+    /// for a column whose values cannot be grouped.
+    pub top_values: Vec<TopValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Profile every column of `relation`, which is anything that can follow
+/// `FROM`: a quoted table name, or a parenthesised query with an alias.
+pub fn profile(connection: &Connection, relation: &str) -> Result<Profile> {
+    let mut prepared = connection.prepare(&format!(
+        "SELECT column_name, column_type, min, max, approx_unique, q25, q50, q75, count,
+                null_percentage::DOUBLE
+         FROM (SUMMARIZE SELECT * FROM {})",
+        relation
+    ))?;
+    let mut rows = 0;
+    let mut columns = vec![];
+    let summaries = prepared.query_map([], |row| {
+        let quantiles: Vec<Option<String>> = vec![row.get(5)?, row.get(6)?, row.get(7)?];
+        Ok((
+            ColumnProfile {
+                name: row.get(0)?,
+                data_type: row.get(1)?,
+                min: row.get(2)?,
+                max: row.get(3)?,
+                approx_distinct: row.get::<_, Option<i64>>(4)?.and_then(|n| u64::try_from(n).ok()),
+                null_percentage: row.get(9)?,
+                quantiles: quantiles.into_iter().flatten().collect(),
+                top_values: vec![],
+            },
+            row.get::<_, Option<i64>>(8)?,
+        ))
+    })?;
+    for summary in summaries {
+        let (column, count) = summary?;
+        rows = count.and_then(|n| u64::try_from(n).ok()).unwrap_or(rows);
+        columns.push(column);
+    }
+    // without them when they cannot be had, the summaries still being worth
+    // having
+    let names: Vec<String> = columns.iter().map(|column| column.name.clone()).collect();
+    let mut top = top_values(connection, relation, &names).unwrap_or_default();
+    for column in &mut columns {
+        column.top_values = top.remove(&column.name).unwrap_or_default();
+    }
+    Ok(Profile { rows, columns })
+}
+
+/// The most frequent values of every one of `columns`, by column, in one
+/// scan: each column as text, unpivoted into a name and value per cell, and
+/// the cells counted. UNPIVOT leaves the nulls out.
+fn top_values(connection: &Connection, relation: &str, columns: &[String]) -> Result<HashMap<String, Vec<TopValue>>> {
+    if columns.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let as_text: Vec<String> = columns
+        .iter()
+        .map(|column| format!("CAST({0} AS VARCHAR) AS {0}", quote_ident(column)))
+        .collect();
+    let mut prepared = connection.prepare(&format!(
+        "SELECT column_name, column_value, frequency FROM (
+             SELECT column_name, column_value, count(*) AS frequency,
+                    row_number() OVER (PARTITION BY column_name ORDER BY count(*) DESC, column_value) AS place
+             FROM (UNPIVOT (SELECT {} FROM {}) ON COLUMNS(*) INTO NAME column_name VALUE column_value)
+             GROUP BY column_name, column_value
+         )
+         WHERE place <= {}
+         ORDER BY column_name, place",
+        as_text.join(", "),
+        relation,
+        TOP_VALUES
+    ))?;
+    let mut values: HashMap<String, Vec<TopValue>> = HashMap::new();
+    let rows = prepared.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            TopValue {
+                value: row.get(1)?,
+                count: row.get::<_, i64>(2)?.max(0) as u64,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (name, value) = row?;
+        values.entry(name).or_default().push(value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_profile_summarizes_every_column() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE plants AS SELECT * FROM (VALUES
+                ('Plant A', 'coal', 120), ('Plant B', 'gas', 340),
+                ('Plant C', 'gas', NULL), ('Plant D', 'gas', 80)) AS t(name, fuel, co2);",
+        )
+        .unwrap();
+
+        let profiled = profile(&conn, "plants").unwrap();
+        assert_eq!(profiled.rows, 4);
+        let co2 = &profiled.columns[2];
+        assert_eq!((co2.name.as_str(), co2.data_type.as_str()), ("co2", "INTEGER"));
+        assert_eq!((co2.min.as_deref(), co2.max.as_deref()), (Some("80"), Some("340")));
+        assert_eq!(co2.null_percentage, Some(25.0));
+        assert_eq!(co2.quantiles.len(), 3);
+        assert_eq!(co2.top_values.len(), 3, "a null is not one of the values");
+
+        let fuel = &profiled.columns[1];
+        assert_eq!(fuel.top_values[0].value, "gas");
+        assert_eq!(fuel.top_values[0].count, 3);
+        assert!(fuel.quantiles.is_empty(), "text has no quartiles");
+
+        // a query profiles the same way, over all of its rows
+        let result = profile(&conn, "(SELECT fuel FROM plants WHERE co2 > 100) AS profiled").unwrap();
+        assert_eq!(result.rows, 2);
+        assert_eq!(result.columns.len(), 1);
+        let counts: Vec<(&str, u64)> =
+            result.columns[0].top_values.iter().map(|top| (top.value.as_str(), top.count)).collect();
+        assert_eq!(counts, [("coal", 1), ("gas", 1)]);
+    }
+}
@@ -0,0 +1,221 @@
+//! Declared foreign keys, read from wherever each kind of database keeps them.
+//!
+//! duckdb lists its own in `duckdb_constraints()`. SQLite keeps them behind
+//! `pragma_foreign_key_list`, which duckdb's scanner does not pass on, so the
+//! attached file is asked directly through the scanner's `sqlite_query`.
+//! Postgres has them in `pg_constraint`, asked through `postgres_query` the
+//! same way. All of it is best-effort: a key that cannot be read only means
+//! that no join is offered along it, and the table is listed all the same.
+
+use crate::{DbType, Input};
+use duckdb::Connection;
+use eyre::Result;
+use std::collections::HashMap;
+
+/// A table by catalog, schema and name, as information_schema names it.
+pub(crate) type TableKey = (String, String, String);
+
+/// A foreign key as its database declares it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DeclaredKey {
+    /// The referencing columns, in the key's order.
+    pub columns: Vec<String>,
+    pub references: TableKey,
+    /// The referenced columns, one for each of `columns`.
+    pub referenced_columns: Vec<String>,
+}
+
+/// Lists are read joined by this, which no column name has, rather than as
+/// duckdb LISTs.
+const SEPARATOR: char = '\u{1f}';
+
+/// Every foreign key the connection's databases declare, by the table that
+/// declares it.
+pub(crate) fn declared_keys(
+    connection: &Connection,
+    databases: &HashMap<String, Input>,
+) -> HashMap<TableKey, Vec<DeclaredKey>> {
+    let mut keys: HashMap<TableKey, Vec<DeclaredKey>> = HashMap::new();
+    let mut add = |table: TableKey, key: DeclaredKey| {
+        let declared = keys.entry(table).or_default();
+        // a scanner may list what is also read here directly
+        if !declared.contains(&key) {
+            declared.push(key);
+        }
+    };
+
+    let own = query_keys(
+        connection,
+        "select database_name, schema_name, table_name, array_to_string(constraint_column_names, chr(31)),
+                database_name, schema_name, referenced_table, array_to_string(referenced_column_names, chr(31))
+         from duckdb_constraints() where constraint_type = 'FOREIGN KEY'",
+        None,
+    );
+    for (table, key) in own.unwrap_or_default() {
+        add(table, key);
+    }
+
+    for input in databases.values() {
+        let read = match input.db_type() {
+            DbType::Sqlite => sqlite_keys(connection, &crate::sqlite_catalog(input), &input.name),
+            DbType::Postgres => query_keys(connection, &postgres_sql(&input.name), Some(&input.name)),
+            _ => continue,
+        };
+        match read {
+            Ok(read) => {
+                for (table, key) in read {
+                    add(table, key);
+                }
+            }
+            Err(e) => eprintln!("Could not read the foreign keys of {}: {}", input.name, e),
+        }
+    }
+    keys
+}
+
+/// Run a query whose rows are the referencing table's catalog, schema, name
+/// and columns, then the same for the referenced one. A `catalog` given
+/// replaces the two catalogs, for a query run inside that database.
+fn query_keys(connection: &Connection, sql: &str, catalog: Option<&str>) -> Result<Vec<(TableKey, DeclaredKey)>> {
+    let split = |joined: String| -> Vec<String> { joined.split(SEPARATOR).map(str::to_string).collect() };
+    let mut prepared = connection.prepare(sql)?;
+    let rows = prepared.query_map([], |row| {
+        let table: TableKey = (row.get(0)?, row.get(1)?, row.get(2)?);
+        let references: TableKey = (row.get(4)?, row.get(5)?, row.get(6)?);
+        Ok((table, split(row.get(3)?), references, split(row.get(7)?)))
+    })?;
+    let mut keys = vec![];
+    for row in rows {
+        let (mut table, columns, mut references, referenced_columns) = row?;
+        if let Some(catalog) = catalog {
+            table.0 = catalog.to_string();
+            references.0 = catalog.to_string();
+        }
+        keys.push((table, DeclaredKey { columns, references, referenced_columns }));
+    }
+    Ok(keys)
+}
+
+fn postgres_sql(name: &str) -> String {
+    let columns = |key: &str, relation: &str| {
+        format!(
+            "array_to_string(array(select a.attname from unnest(co.{0}) with ordinality k(attnum, i) \
+             join pg_attribute a on a.attrelid = co.{1} and a.attnum = k.attnum order by k.i), chr(31))",
+            key, relation
+        )
+    };
+    let inner = format!(
+        "select '', cn.nspname, cl.relname, {}, '', fn.nspname, fl.relname, {} \
+         from pg_constraint co \
+         join pg_class cl on cl.oid = co.conrelid join pg_namespace cn on cn.oid = cl.relnamespace \
+         join pg_class fl on fl.oid = co.confrelid join pg_namespace fn on fn.oid = fl.relnamespace \
+         where co.contype = 'f'",
+        columns("conkey", "conrelid"),
+        columns("confkey", "confrelid")
+    );
+    format!(
+        "select * from postgres_query({}, {})",
+        crate::quote_literal(name),
+        crate::quote_literal(&inner)
+    )
+}
+
+/// An attached SQLite file's keys, by asking SQLite. A key that names no
+/// referenced columns refers to the other table's primary key, whose columns
+/// pair up with its own in order. The file is asked as `attached`, and its
+/// tables named in `name`, which differ for one read typed.
+fn sqlite_keys(connection: &Connection, attached: &str, name: &str) -> Result<Vec<(TableKey, DeclaredKey)>> {
+    let inner = "SELECT m.name, f.id, f.\"table\", f.\"from\", \
+                 coalesce(f.\"to\", (SELECT p.name FROM pragma_table_info(f.\"table\") AS p WHERE p.pk = f.seq + 1)) \
+                 FROM sqlite_master AS m, pragma_foreign_key_list(m.name) AS f \
+                 WHERE m.type = 'table' ORDER BY m.name, f.id, f.seq";
+    let sql = format!(
+        "select * from sqlite_query({}, {})",
+        crate::quote_literal(attached),
+        crate::quote_literal(inner)
+    );
+    let mut prepared = connection.prepare(&sql)?;
+    let rows = prepared.query_map([], |row| {
+        Ok((text(row.get(0)?), text(row.get(1)?), text(row.get(2)?), text(row.get(3)?), text(row.get(4)?)))
+    })?;
+    let in_sqlite = |table: String| (name.to_string(), "main".to_string(), table);
+
+    // a row per column, a key's in a run of their own
+    let mut keys: Vec<(TableKey, DeclaredKey)> = vec![];
+    let mut current = None;
+    for row in rows {
+        let (Some(table), id, Some(referenced), Some(column), referenced_column) = row? else {
+            continue;
+        };
+        if current.as_ref() != Some(&(table.clone(), id.clone())) {
+            let key = DeclaredKey { columns: vec![], references: in_sqlite(referenced), referenced_columns: vec![] };
+            keys.push((in_sqlite(table.clone()), key));
+            current = Some((table, id));
+        }
+        if let Some((_, key)) = keys.last_mut() {
+            key.columns.push(column);
+            key.referenced_columns.extend(referenced_column);
+        }
+    }
+    Ok(keys)
+}
+
+/// A value from `sqlite_query` as text. A column SQLite declares no type for
+/// — a pragma's, or an expression — reaches duckdb as a BLOB of its text.
+fn text(value: duckdb::types::Value) -> Option<String> {
+    use duckdb::types::Value;
+    match value {
+        Value::Text(text) => Some(text),
+        Value::Blob(bytes) => String::from_utf8(bytes).ok(),
+        Value::BigInt(number) => Some(number.to_string()),
+        Value::Int(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn foreign_keys_are_read_from_duckdb_and_sqlite() {
+        let dir = std::env::temp_dir().join(format!("sqlnow-keys-test-{}", crate::random_id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shop.sqlite");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT);
+                 CREATE TABLE orders (id INTEGER PRIMARY KEY, customer INTEGER REFERENCES customers);",
+            )
+            .unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE units (id INTEGER PRIMARY KEY, name VARCHAR);
+             CREATE TABLE readings (unit_id INTEGER REFERENCES units (id), mw DOUBLE);
+             INSTALL sqlite; LOAD sqlite;
+             ATTACH {} AS shop (TYPE sqlite, READ_ONLY);",
+            crate::quote_literal(&path.to_string_lossy())
+        ))
+        .unwrap();
+        let shop = Input {
+            name: "shop".to_string(),
+            uri: path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let keys = declared_keys(&conn, &HashMap::from([("shop".to_string(), shop)]));
+
+        let table = |catalog: &str, name: &str| (catalog.to_string(), "main".to_string(), name.to_string());
+        let readings = &keys[&table("memory", "readings")];
+        assert_eq!(readings[0].columns, ["unit_id"]);
+        assert_eq!(readings[0].references, table("memory", "units"));
+        assert_eq!(readings[0].referenced_columns, ["id"]);
+
+        // no columns named means the other table's primary key
+        let orders = &keys[&table("shop", "orders")];
+        assert_eq!(orders[0].columns, ["customer"]);
+        assert_eq!(orders[0].references, table("shop", "customers"));
+        assert_eq!(orders[0].referenced_columns, ["id"]);
+    }
+}
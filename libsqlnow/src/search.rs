@@ -0,0 +1,153 @@
+//! Finding a table or a column by name across every input.
+//!
+//! The search runs over the catalog the sidebar shows, so whatever
+//! `--only`/`--except` hid is not found either, and it costs nothing while the
+//! catalog is cached. Each word of the query has to match somewhere — in the
+//! column's name, its table's, or its type — and the better the matches, the
+//! higher the hit: a whole name beats a whole word of one, which beats a
+//! prefix, then a substring, then the letters in order with others between
+//! them. So `cust email` finds `customers.email` and `crm.customer_email`
+//! alike, and `ord_dt` still finds `order_date`.
+
+use crate::Tab;
+use serde::Serialize;
+
+/// How many hits a search returns when the caller does not say.
+pub const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// The table, by its name in the sidebar.
+    pub table: String,
+    /// The sidebar section it is listed under, if any.
+    pub section: Option<String>,
+    /// The column that matched, or none for a match on the table itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// Higher is better; only comparable within one search.
+    pub score: u32,
+}
+
+/// The tables and columns of `tabs` that match `query`, best first and at
+/// most `limit` of them.
+pub fn search(tabs: &[Tab], query: &str, limit: usize) -> Vec<SearchHit> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        return vec![];
+    }
+    let mut hits = vec![];
+    for tab in tabs {
+        let Some(meta) = tab.schema.as_ref() else { continue };
+        let table = tab.name.to_lowercase();
+        let hit = |column: Option<&(String, String)>, score: u32| SearchHit {
+            table: tab.name.clone(),
+            section: tab.section.clone(),
+            column: column.map(|(name, _)| name.clone()),
+            data_type: column.map(|(_, data_type)| data_type.clone()),
+            score,
+        };
+
+        // the table itself, on its name alone
+        if let Some(score) = all_terms(&terms, |term| score_term(term, &table)) {
+            hits.push(hit(None, score * 2));
+        }
+        // each column, on its name foremost, and its table's and its type's
+        for field in &meta.fields {
+            let (name, data_type) = (field.0.to_lowercase(), field.1.to_lowercase());
+            let best = |term: &str| {
+                [
+                    score_term(term, &name).map(|score| score * 2),
+                    score_term(term, &table),
+                    score_term(term, &data_type),
+                ]
+                .into_iter()
+                .flatten()
+                .max()
+            };
+            // a column that matches only through its table is already found
+            // as the table
+            if terms
+                .iter()
+                .all(|term| score_term(term, &name).is_none() && score_term(term, &data_type).is_none())
+            {
+                continue;
+            }
+            if let Some(score) = all_terms(&terms, best) {
+                hits.push(hit(Some(field), score));
+            }
+        }
+    }
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.table.cmp(&b.table))
+            .then_with(|| a.column.cmp(&b.column))
+    });
+    hits.truncate(limit);
+    hits
+}
+
+/// The sum of each term's score, or `None` when one of them matches nothing.
+fn all_terms(terms: &[String], score: impl Fn(&str) -> Option<u32>) -> Option<u32> {
+    terms.iter().map(|term| score(term)).sum()
+}
+
+/// How well one lowercased term matches one lowercased name, if at all. A
+/// name's words are what `_`, `.`, `-` and spaces split it into.
+fn score_term(term: &str, name: &str) -> Option<u32> {
+    if name == term {
+        return Some(100);
+    }
+    let mut words = name.split(['_', '.', '-', ' ']).filter(|word| !word.is_empty());
+    if words.clone().any(|word| word == term) {
+        return Some(80);
+    }
+    if name.starts_with(term) {
+        return Some(70);
+    }
+    if words.any(|word| word.starts_with(term)) {
+        return Some(60);
+    }
+    if name.contains(term) {
+        return Some(40);
+    }
+    // the letters in order, worth less the further they are spread; too
+    // loose to mean anything for one or two letters
+    if term.chars().count() < 3 {
+        return None;
+    }
+    let mut chars = name.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for wanted in term.chars() {
+        let (at, _) = chars.find(|(_, c)| *c == wanted)?;
+        first.get_or_insert(at);
+        last = at;
+    }
+    let span = (last - first.unwrap_or(0) + 1) as u32;
+    Some(10 + 20 * term.len() as u32 / span.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_score_whole_names_above_words_above_fragments() {
+        let scores: Vec<Option<u32>> = ["email", "customer_email", "emails", "user_emails", "e_mail_addr", "phone"]
+            .iter()
+            .map(|name| score_term("email", name))
+            .collect();
+        assert_eq!(scores[0], Some(100));
+        assert_eq!(scores[1], Some(80));
+        assert_eq!(scores[2], Some(70));
+        assert_eq!(scores[3], Some(60));
+        // only the letters in order
+        assert!(scores[4].unwrap() < 40);
+        assert_eq!(scores[5], None);
+        assert_eq!(score_term("od", "order_date"), None, "too short to match loosely");
+        assert!(score_term("ord_dt", "order_date").is_some());
+    }
+}
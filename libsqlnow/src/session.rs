@@ -7,7 +7,10 @@
 //! duration of each operation, so external processes (`sqlnow exec`, a duckdb
 //! CLI) can read and write it between server operations.
 
-use crate::{run_query, sniff_db_type, DbType, Input, TableData};
+use crate::{
+    glob_base, is_glob, run_query, sniff_db_type, strip_compression, DbType, GeometryFormat, Input,
+    InputOptions, TableData, STDIN_URI,
+};
 use duckdb::{params, Connection};
 use eyre::Result;
 use std::fmt;
@@ -17,8 +20,9 @@ use std::path::{Path, PathBuf};
 /// one session per file with no version marker; format 2 adds `format` and
 /// `sessions` and gives every other row a `session` column, so one database
 /// can hold many; format 3 adds `sessions.url`, where a running server
-/// publishes its address. Bump only alongside a migration in [`ensure_format`].
-const FORMAT_VERSION: i64 = 3;
+/// publishes its address; format 4 adds `inputs.options`, each input's reader
+/// options as JSON. Bump only alongside a migration in [`ensure_format`].
+const FORMAT_VERSION: i64 = 4;
 
 const SESSION_SCHEMA: &str = "
     CREATE TABLE IF NOT EXISTS format(version INTEGER NOT NULL);
@@ -33,7 +37,7 @@ const SESSION_SCHEMA: &str = "
     CREATE TABLE IF NOT EXISTS meta(session TEXT NOT NULL, key TEXT NOT NULL, value TEXT, PRIMARY KEY (session, key));
     CREATE TABLE IF NOT EXISTS queries(session TEXT NOT NULL, pos INTEGER NOT NULL, name TEXT NOT NULL, sql TEXT NOT NULL, PRIMARY KEY (session, name));
     CREATE TABLE IF NOT EXISTS history(session TEXT NOT NULL, \"at\" TIMESTAMP NOT NULL DEFAULT now(), sql TEXT NOT NULL);
-    CREATE TABLE IF NOT EXISTS inputs(session TEXT NOT NULL, kind TEXT NOT NULL, name TEXT NOT NULL, uri TEXT NOT NULL, tables TEXT[], except_tables TEXT[]);
+    CREATE TABLE IF NOT EXISTS inputs(session TEXT NOT NULL, kind TEXT NOT NULL, name TEXT NOT NULL, uri TEXT NOT NULL, tables TEXT[], except_tables TEXT[], options TEXT);
 ";
 
 const LOCK_RETRIES: u32 = 5;
@@ -517,25 +521,43 @@ impl Session {
 
     pub fn list_inputs(&self) -> std::result::Result<Vec<(String, Input)>, SessionError> {
         self.with_conn(|conn| {
-            let mut stmt = conn
-                .prepare("SELECT kind, name, uri, tables, except_tables FROM inputs WHERE session = ?")?;
+            let mut stmt = conn.prepare(
+                "SELECT kind, name, uri, tables, except_tables, options FROM inputs WHERE session = ?",
+            )?;
             let rows = stmt.query_map(params![self.id], |row| {
                 let kind: String = row.get(0)?;
                 let name: String = row.get(1)?;
                 let uri: String = row.get(2)?;
                 let only: duckdb::types::Value = row.get(3)?;
                 let except: duckdb::types::Value = row.get(4)?;
-                Ok((
+                let options: Option<String> = row.get(5)?;
+                Ok((kind, name, uri, only, except, options))
+            })?;
+            let mut inputs = vec![];
+            for (kind, name, uri, only, except, options) in rows.filter_map(|r| r.ok()) {
+                // an input replayed without its options would read its file
+                // differently, so one whose options cannot be read — written
+                // by a newer sqlnow, say — is left out, and the rest kept
+                let options = match InputOptions::from_stored(options.as_deref()) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        eprintln!("warning: could not read the options of input {}: {}", name, e);
+                        continue;
+                    }
+                };
+                inputs.push((
                     kind,
                     Input {
                         name,
                         uri,
                         tables: table_list_from_value(only),
                         except: table_list_from_value(except),
+                        options,
+                        ..Default::default()
                     },
-                ))
-            })?;
-            Ok(rows.filter_map(|r| r.ok()).collect())
+                ));
+            }
+            Ok(inputs)
         })
     }
 
@@ -545,14 +567,7 @@ impl Session {
             conn.execute_batch("BEGIN")?;
             conn.execute("DELETE FROM inputs WHERE session = ?", params![self.id])?;
             for (kind, input) in entries {
-                conn.execute(
-                    &format!(
-                        "INSERT INTO inputs(session, kind, name, uri, tables, except_tables) VALUES (?, ?, ?, ?, {}, {})",
-                        table_list_literal(&input.tables),
-                        table_list_literal(&input.except)
-                    ),
-                    params![self.id, kind, input.name, absolute_uri(&input.uri)],
-                )?;
+                insert_input(conn, &self.id, kind, input)?;
             }
             conn.execute_batch("COMMIT")?;
             touch_changed(conn, &self.id)?;
@@ -584,14 +599,7 @@ impl Session {
                 "DELETE FROM inputs WHERE session = ? AND name = ?",
                 params![self.id, input.name],
             )?;
-            conn.execute(
-                &format!(
-                    "INSERT INTO inputs(session, kind, name, uri, tables, except_tables) VALUES (?, ?, ?, ?, {}, {})",
-                    table_list_literal(&input.tables),
-                    table_list_literal(&input.except)
-                ),
-                params![self.id, kind, input.name, absolute_uri(&input.uri)],
-            )?;
+            insert_input(conn, &self.id, kind, input)?;
             touch_changed(conn, &self.id)?;
             Ok(())
         })
@@ -613,7 +621,7 @@ impl Session {
     /// Single statements return rows; multi-statement batches return an
     /// empty result.
     pub fn raw_sql(&self, sql: &str) -> std::result::Result<TableData, SessionError> {
-        self.with_conn(|conn| run_raw(conn, sql))
+        self.with_conn(|conn| run_raw(conn, sql, GeometryFormat::Wkt))
     }
 }
 
@@ -622,17 +630,18 @@ impl Session {
 /// inspecting and repairing one is exactly what `exec` is for — so it does
 /// not bind to a single session the way [`Session::open`] does. A file that
 /// holds no session yet gets one, so `... SELECT id FROM sessions` works on a
-/// freshly created session file.
-pub fn exec_sql(path: &Path, sql: &str) -> Result<TableData> {
+/// freshly created session file. Geometry is written as `geometry` says,
+/// the same as `sqlnow sql` writes it.
+pub fn exec_sql(path: &Path, sql: &str, geometry: GeometryFormat) -> Result<TableData> {
     let conn = Session::open_database(path)?;
     if session_ids(&conn)?.is_empty() {
         insert_session(&conn, &random_id(), None, None)?;
     }
-    run_raw(&conn, sql).map_err(|e| eyre::eyre!("{}", e))
+    run_raw(&conn, sql, geometry).map_err(|e| eyre::eyre!("{}", e))
 }
 
-fn run_raw(conn: &Connection, sql: &str) -> std::result::Result<TableData, SessionError> {
-    match run_query(sql, conn, usize::MAX) {
+fn run_raw(conn: &Connection, sql: &str, geometry: GeometryFormat) -> std::result::Result<TableData, SessionError> {
+    match run_query(sql, conn, usize::MAX, geometry) {
         Ok(table_data) => Ok(table_data),
         Err(prepare_error) => {
             // multi-statement input (or a genuine error, which will surface
@@ -644,6 +653,25 @@ fn run_raw(conn: &Connection, sql: &str) -> std::result::Result<TableData, Sessi
     }
 }
 
+/// Record one input under a session, its uri made absolute so the session
+/// replays from anywhere.
+fn insert_input(
+    conn: &Connection,
+    session: &str,
+    kind: &str,
+    input: &Input,
+) -> duckdb::Result<usize> {
+    conn.execute(
+        &format!(
+            "INSERT INTO inputs(session, kind, name, uri, tables, except_tables, options) \
+             VALUES (?, ?, ?, ?, {}, {}, ?)",
+            table_list_literal(&input.tables),
+            table_list_literal(&input.except)
+        ),
+        params![session, kind, input.name, absolute_uri(&input.uri), input.options.to_stored()],
+    )
+}
+
 /// A duckdb list literal for a table filter — names are quoted, so any
 /// characters (including commas) survive storage.
 fn table_list_literal(tables: &[String]) -> String {
@@ -704,12 +732,14 @@ fn ensure_format(conn: &Connection, path: &Path) -> Result<()> {
         None => {
             conn.execute("INSERT INTO format(version) VALUES (?)", params![FORMAT_VERSION])?;
         }
-        Some(2) => {
-            // format 3 only adds a column, so the rows carry over untouched
-            conn.execute_batch(
-                "ALTER TABLE sessions ADD COLUMN IF NOT EXISTS url TEXT;
-                 UPDATE format SET version = 3;",
-            )?;
+        Some(found @ (2 | 3)) => {
+            // formats 3 and 4 each only add a column, so the rows carry over
+            // untouched
+            if found < 3 {
+                conn.execute_batch("ALTER TABLE sessions ADD COLUMN IF NOT EXISTS url TEXT;")?;
+            }
+            conn.execute_batch("ALTER TABLE inputs ADD COLUMN IF NOT EXISTS options TEXT;")?;
+            conn.execute("UPDATE format SET version = ?", params![FORMAT_VERSION])?;
         }
         Some(found) if found > FORMAT_VERSION => {
             return Err(eyre::eyre!(
@@ -738,14 +768,18 @@ fn migrate_format_1(conn: &Connection) -> Result<()> {
     conn.execute("INSERT INTO format(version) VALUES (?)", params![FORMAT_VERSION])?;
     conn.execute("INSERT INTO sessions(id) VALUES (?)", params![id])?;
 
-    // a format 1 file written before --except existed may lack the column
-    conn.execute_batch("ALTER TABLE inputs ADD COLUMN IF NOT EXISTS except_tables TEXT[];")?;
+    // a format 1 file written before --except existed may lack the column,
+    // and none has options
+    conn.execute_batch(
+        "ALTER TABLE inputs ADD COLUMN IF NOT EXISTS except_tables TEXT[];
+         ALTER TABLE inputs ADD COLUMN IF NOT EXISTS options TEXT;",
+    )?;
 
     for (table, columns, rebuilt) in [
         ("meta", "key, value", "session TEXT NOT NULL, key TEXT NOT NULL, value TEXT, PRIMARY KEY (session, key)"),
         ("queries", "pos, name, sql", "session TEXT NOT NULL, pos INTEGER NOT NULL, name TEXT NOT NULL, sql TEXT NOT NULL, PRIMARY KEY (session, name)"),
         ("history", "\"at\", sql", "session TEXT NOT NULL, \"at\" TIMESTAMP NOT NULL DEFAULT now(), sql TEXT NOT NULL"),
-        ("inputs", "kind, name, uri, tables, except_tables", "session TEXT NOT NULL, kind TEXT NOT NULL, name TEXT NOT NULL, uri TEXT NOT NULL, tables TEXT[], except_tables TEXT[]"),
+        ("inputs", "kind, name, uri, tables, except_tables, options", "session TEXT NOT NULL, kind TEXT NOT NULL, name TEXT NOT NULL, uri TEXT NOT NULL, tables TEXT[], except_tables TEXT[], options TEXT"),
     ] {
         conn.execute_batch(&format!("CREATE TABLE {}_2({});", table, rebuilt))?;
         // 'id' moves out of meta and into the sessions row
@@ -893,14 +927,7 @@ fn upgrade_legacy_sidecar(path: &Path) -> Result<()> {
         let id = id.unwrap_or_else(random_id);
         conn.execute("INSERT INTO sessions(id) VALUES (?)", params![id])?;
         for (kind, input) in &entries {
-            conn.execute(
-                &format!(
-                    "INSERT INTO inputs(session, kind, name, uri, tables, except_tables) VALUES (?, ?, ?, ?, {}, {})",
-                    table_list_literal(&input.tables),
-                    table_list_literal(&input.except)
-                ),
-                params![id, kind, input.name, absolute_uri(&input.uri)],
-            )?;
+            insert_input(&conn, &id, kind, input)?;
         }
     }
     std::fs::rename(&tmp, path)?;
@@ -1006,6 +1033,8 @@ pub fn input_into_parts(input: &str) -> Result<Input> {
             uri: input.to_owned(),
             tables: vec![],
             except: vec![],
+            options: InputOptions::default(),
+            ..Default::default()
         });
     }
 
@@ -1020,6 +1049,8 @@ pub fn input_into_parts(input: &str) -> Result<Input> {
             uri: not_name,
             tables: vec![],
             except: vec![],
+            options: InputOptions::default(),
+            ..Default::default()
         });
     }
 
@@ -1028,10 +1059,19 @@ pub fn input_into_parts(input: &str) -> Result<Input> {
         None => (not_name, vec![]),
     };
 
-    Ok(Input { name, uri, tables, except: vec![] })
+    Ok(Input { name, uri, tables, except: vec![], options: InputOptions::default() })
 }
 
 pub fn default_name_and_check(input: &mut Input) -> Result<()> {
+    // what was piped in was read once, when the session was made
+    if input.uri == STDIN_URI {
+        return Err(eyre::eyre!(
+            "\"{}\" was piped in on stdin, which cannot be read again; pipe it in once more \
+             to get it back: ... | sqlnow - --as {}",
+            input.name,
+            input.name
+        ));
+    }
     // A database somewhere else — postgres, mysql — is named after the
     // database itself. Without this the name stayed empty and the attach was
     // built as `AS ""`, which duckdb rejects with a parser error about a
@@ -1055,7 +1095,8 @@ pub fn default_name_and_check(input: &mut Input) -> Result<()> {
     if let Some((_, rest)) = input.uri.split_once("://") {
         if !input.uri.starts_with("sqlite://") {
             if input.name.is_empty() {
-                input.name = name_from_uri(rest).ok_or_else(|| {
+                // a pattern is named after the directory it reads
+                input.name = name_from_uri(glob_base(rest)).ok_or_else(|| {
                     eyre::eyre!("{} needs a name of its own (--as <name>)", input.uri)
                 })?;
             }
@@ -1064,6 +1105,27 @@ pub fn default_name_and_check(input: &mut Input) -> Result<()> {
     }
 
     let path = input.uri.strip_prefix("sqlite://").unwrap_or(&input.uri);
+
+    // A glob cannot be checked file by file, but the directory it reads from
+    // can, and that is the name it gets: `sales/*.parquet` is `sales`.
+    if is_glob(path) {
+        let base = match glob_base(path) {
+            "" => ".",
+            base => base,
+        };
+        if !Path::new(base).is_dir() {
+            return Err(eyre::eyre!("Directory {} does not exist", base));
+        }
+        if input.name.is_empty() {
+            input.name = std::fs::canonicalize(base)
+                .ok()
+                .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+                .map(reserved_safe)
+                .ok_or_else(|| eyre::eyre!("{} needs a name of its own (--as <name>)", path))?;
+        }
+        return Ok(());
+    }
+
     let path_buf = PathBuf::from(path);
 
     if !path_buf.exists() {
@@ -1071,8 +1133,11 @@ pub fn default_name_and_check(input: &mut Input) -> Result<()> {
     }
 
     if input.name.is_empty() {
-        let stem = path_buf.file_stem().expect("is file").to_string_lossy().to_string();
-        input.name = reserved_safe(stem);
+        // sales.csv.gz is sales, not sales.csv
+        let stem = Path::new(strip_compression(path)).file_stem().ok_or_else(|| {
+            eyre::eyre!("{} needs a name of its own (--as <name>)", path)
+        })?;
+        input.name = reserved_safe(stem.to_string_lossy().to_string());
     }
 
     Ok(())
@@ -1127,6 +1192,14 @@ pub fn absolute_uri(uri: &str) -> String {
         }
     } else if uri.contains("://") {
         uri.to_string()
+    } else if is_glob(uri) {
+        // the pattern stays as given; only the directory under it is resolved
+        let base = glob_base(uri);
+        let pattern = uri[base.len()..].trim_start_matches('/');
+        match std::fs::canonicalize(if base.is_empty() { "." } else { base }) {
+            Ok(abs) => format!("{}/{}", abs.display(), pattern),
+            Err(_) => uri.to_string(),
+        }
     } else {
         match std::fs::canonicalize(uri) {
             Ok(abs) => abs.display().to_string(),
@@ -1182,8 +1255,14 @@ impl StoredSession {
         if let Some(path) = &self.path {
             return !Path::new(path).exists();
         }
-        let mut local = self.inputs.iter().filter(|uri| !uri.contains("://")).peekable();
-        local.peek().is_some() && local.all(|uri| !Path::new(uri).exists())
+        // nor can stdin, which resuming explains rather than hides
+        let mut local = self
+            .inputs
+            .iter()
+            .filter(|uri| !uri.contains("://") && *uri != STDIN_URI)
+            .peekable();
+        // a glob is there as long as the directory it reads from is
+        local.peek().is_some() && local.all(|uri| !Path::new(glob_base(uri)).exists())
     }
 }
 
@@ -1309,7 +1388,7 @@ pub fn list_sessions(store: &Path) -> Result<Vec<StoredSession>> {
         "SELECT s.id, s.key, s.path,
                 epoch(now()::TIMESTAMP - s.last_used)::BIGINT,
                 (SELECT count(*) FROM queries q WHERE q.session = s.id),
-                (SELECT list(i.uri) FROM inputs i WHERE i.session = s.id),
+                (SELECT list(i.uri) FROM inputs i WHERE i.session = s.id AND i.kind <> 'sql'),
                 s.url
          FROM sessions s
          ORDER BY s.last_used DESC",
@@ -1418,8 +1497,7 @@ mod tests {
             let mut input = Input {
                 name: String::new(),
                 uri: uri.to_string(),
-                tables: vec![],
-                except: vec![],
+                ..Default::default()
             };
             default_name_and_check(&mut input).map(|_| input.name)
         };
@@ -1434,6 +1512,8 @@ mod tests {
         // rejected for failing to exist locally
         assert_eq!(named("https://example.com/data/plants.parquet").unwrap(), "plants");
         assert_eq!(named("s3://bucket/keys/units.csv").unwrap(), "units");
+        assert_eq!(named("s3://bucket/pipeline/batches.feather").unwrap(), "batches");
+        assert_eq!(named("https://example.com/dumps/orders.avro").unwrap(), "orders");
 
         // and a url with no database in it says so, rather than failing later
         // with something about identifiers
@@ -1441,6 +1521,26 @@ mod tests {
         assert!(complaint.contains("needs a name"), "{}", complaint);
     }
 
+    #[test]
+    fn a_glob_is_named_after_the_directory_it_reads() {
+        let named = |uri: &str| {
+            let mut input = Input { uri: uri.to_string(), ..Default::default() };
+            default_name_and_check(&mut input).map(|_| input.name)
+        };
+        assert_eq!(named("s3://bucket/sales/*.parquet").unwrap(), "sales");
+        assert_eq!(named("s3://bucket/sales/**/*.parquet").unwrap(), "sales");
+
+        let dir = temp_path("globbed");
+        std::fs::create_dir_all(dir.join("units")).unwrap();
+        let pattern = format!("{}/units/*.csv", dir.display());
+        assert_eq!(named(&pattern).unwrap(), "units");
+        // the directory is what can be checked, so that is what is reported
+        let gone = format!("{}/gone/*.csv", dir.display());
+        assert!(named(&gone).unwrap_err().to_string().contains("does not exist"));
+        // and resolving it keeps the pattern, only making the directory absolute
+        assert!(absolute_uri(&pattern).ends_with("/units/*.csv"));
+    }
+
     #[test]
     fn legacy_sidecar_upgrades_in_place() {
         let path = temp_path("legacy.sqlnow");
@@ -1474,6 +1574,7 @@ mod tests {
                     // commas and quotes in table names survive storage
                     tables: vec!["a".to_string(), "weird,name".to_string(), "it's".to_string()],
                     except: vec!["audit,log".to_string()],
+                    ..Default::default()
                 },
             )])
             .unwrap();
@@ -1482,6 +1583,44 @@ mod tests {
         assert_eq!(inputs[0].1.except, vec!["audit,log"]);
     }
 
+    #[test]
+    fn inputs_round_trip_with_their_reader_options() {
+        let session = Session::in_memory().unwrap();
+        let mut options = InputOptions::default();
+        options.set_assignment("delim=|").unwrap();
+        options.set_assignment("types=amount:DECIMAL(10,2)").unwrap();
+        session
+            .add_input(
+                "view",
+                &Input {
+                    name: "sales".to_string(),
+                    uri: "s3://bucket/sales.txt".to_string(),
+                    options: options.clone(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(session.list_inputs().unwrap()[0].1.options, options);
+    }
+
+    #[test]
+    fn an_input_with_unreadable_options_is_left_out_alone() {
+        let session = Session::in_memory().unwrap();
+        for name in ["sales", "units"] {
+            let input = Input { name: name.to_string(), uri: format!("{}.csv", name), ..Default::default() };
+            session.add_input("view", &input).unwrap();
+        }
+        // as a newer sqlnow, with an option this one does not know, might leave it
+        session
+            .with_conn(|conn| {
+                conn.execute("UPDATE inputs SET options = ? WHERE name = 'units'", params![r#"{"delimter": ","}"#])?;
+                Ok(())
+            })
+            .unwrap();
+        let names: Vec<String> = session.list_inputs().unwrap().into_iter().map(|(_, input)| input.name).collect();
+        assert_eq!(names, vec!["sales"]);
+    }
+
     #[test]
     fn clobbered_sql_is_preserved_in_history() {
         let session = Session::in_memory().unwrap();
@@ -1565,7 +1704,7 @@ mod tests {
     }
 
     #[test]
-    fn a_format_2_session_gains_the_url_column() {
+    fn a_format_2_session_gains_the_later_columns() {
         let path = temp_path("v2.sqlnow");
         {
             // format 2: everything but sessions.url
@@ -1598,6 +1737,16 @@ mod tests {
         let url: Option<String> =
             conn.query_row("SELECT url FROM sessions", [], |row| row.get(0)).unwrap();
         assert_eq!(url, None);
+        // and so is format 4's, which inputs recorded before it never needed
+        let options: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM information_schema.columns \
+                 WHERE table_name = 'inputs' AND column_name = 'options'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(options, 1);
 
         drop(conn);
         set_session_url(&path, "keepme", Some("http://127.0.0.1:9999")).unwrap();
@@ -14,6 +14,9 @@ async fn main() -> Result<()> {
     // bind before announcing anything: a failed bind (port already in use)
     // must not print "Server running" or open a browser tab at a dead URL
     let host = prepared.host.clone();
+    if cli.watch {
+        actix_web::rt::spawn(libsqlnow::watch_inputs(prepared.app_data.clone()));
+    }
     let (server, addr) = serve(prepared.app_data, &host, prepared.port.unwrap_or(8080))?;
 
     // before anything is announced: a stop that arrives immediately must still
@@ -204,3 +204,55 @@ fn a_server_can_list_the_other_sessions() {
     let here_now = again["sessions"].as_array().unwrap().iter().find(|s| s["current"] == true);
     assert_eq!(here_now.unwrap()["missing"], false, "{}", again);
 }
+
+#[test]
+fn tables_and_results_can_be_profiled() {
+    let space = Workspace::new("profile");
+    let server = space.start(&[&space.csv("plants.csv").to_string_lossy()]);
+
+    let profiled = server.get("/api/tables/plants/profile");
+    assert_eq!(profiled["rows"], 2);
+    let co2 = &profiled["columns"][1];
+    assert_eq!(co2["name"], "co2");
+    assert_eq!((co2["min"].as_str(), co2["max"].as_str()), (Some("120"), Some("340")));
+    assert_eq!(co2["null_percentage"], 0.0);
+    assert_eq!(co2["quantiles"].as_array().map(Vec::len), Some(3));
+    assert_eq!(profiled["columns"][0]["top_values"].as_array().map(Vec::len), Some(2));
+    assert_eq!(server.status("/api/tables/nothing/profile"), 404);
+
+    // a result is profiled whole, not just the page of it that is shown
+    let result = server.query_profiled("SELECT name FROM plants WHERE co2 > 200");
+    assert_eq!(result["profile"]["rows"], 1);
+    assert_eq!(result["profile"]["columns"][0]["top_values"][0]["value"], "Plant B");
+    assert!(result["error"].is_null() && result["profile_error"].is_null(), "{}", result);
+    assert!(server.query("SELECT 1")["profile"].is_null(), "only when asked for");
+}
+
+#[test]
+fn tables_and_columns_can_be_searched_for() {
+    let space = Workspace::new("search");
+    space.write("customers.csv", "id,email,signed_up\n1,a@example.com,2024-03-01\n");
+    let legacy = space.sqlite("legacy.sqlite", &["units", "audit_log"]);
+    let server = space.start(&[
+        &space.csv("plants.csv").to_string_lossy(),
+        "-v", "customers.csv",
+        "-v", &legacy.to_string_lossy(), "--as", "legacy", "--except", "audit_log",
+    ]);
+
+    let found = server.get("/api/search?q=cust%20email");
+    let first = &found["results"][0];
+    assert_eq!((first["table"].as_str(), first["column"].as_str()), (Some("customers"), Some("email")));
+
+    // a table is found by its name, a column by its type, and loosely
+    assert_eq!(server.get("/api/search?q=plants")["results"][0]["table"], "plants");
+    let dated = server.get("/api/search?q=date");
+    assert_eq!(dated["results"][0]["column"], "signed_up");
+    assert_eq!(server.get("/api/search?q=sgnd")["results"][0]["column"], "signed_up");
+    let units = server.get("/api/search?q=units");
+    assert_eq!(units["results"][0]["table"], "legacy.units");
+    assert_eq!(units["results"][0]["section"], "legacy");
+
+    // what --except hid stays hidden
+    assert!(server.get("/api/search?q=audit")["results"].as_array().unwrap().is_empty());
+    assert_eq!(server.status("/api/search?q="), 400);
+}
@@ -269,3 +269,41 @@ fn the_cli_says_when_it_stopped_early() {
     let out = space.run(&["sql", "scratch.sqlnow", "SELECT 1 AS i", "--limit", "9", "-f", "csv"]);
     assert_eq!(String::from_utf8_lossy(&out.stderr), "");
 }
+
+#[test]
+fn geometry_is_written_as_wkt_or_geojson() {
+    let space = Workspace::new("geometry");
+    let sites = space.write(
+        "sites.geojson",
+        r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "Plant A"},
+             "geometry": {"type": "Point", "coordinates": [1.5, 52]}}
+        ]}"#,
+    );
+    let sql = "SELECT name, geom FROM sites";
+
+    // read through ST_Read, and written as WKT everywhere rather than as the
+    // WKB duckdb hands back
+    let server = space.start(&[&sites.to_string_lossy()]);
+    assert_eq!(server.query(sql)["table_data"]["rows"][0][1], "POINT (1.5 52)");
+    assert_eq!(server.export(sql, "csv"), "name,geom\nPlant A,POINT (1.5 52)\n");
+    server.stop();
+
+    let server = space.start(&["--geometry-format", "geojson", &sites.to_string_lossy()]);
+    let cell = server.query(sql)["table_data"]["rows"][0][1].as_str().unwrap().to_string();
+    let geojson: serde_json::Value = serde_json::from_str(&cell).expect("a geojson geometry");
+    assert_eq!(geojson, serde_json::json!({"type": "Point", "coordinates": [1.5, 52.0]}));
+    server.stop();
+
+    // and from the command line, where the flag goes after the subcommand too
+    space.exec(&space.path().join("scratch.sqlnow"), "SELECT 1");
+    let query = format!("SELECT geom FROM ST_Read('{}')", sites.display());
+    let wkt = space.run_text(&["sql", "scratch.sqlnow", &query, "-f", "csv"]);
+    assert_eq!(wkt.trim(), "geom\nPOINT (1.5 52)");
+    let geojson = space.run_text(&["sql", "scratch.sqlnow", &query, "-f", "csv", "--geometry-format", "geojson"]);
+    assert!(geojson.contains("\"\"Point\"\""), "{}", geojson);
+
+    // `exec` prints through the same writer
+    let wkt = space.exec(&space.path().join("scratch.sqlnow"), &query);
+    assert_eq!(wkt, "geom\nPOINT (1.5 52)");
+}
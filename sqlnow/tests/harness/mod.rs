@@ -8,7 +8,7 @@
 //! before these tests run and passes its path in, so there is nothing to
 //! compile here and nothing to keep in step.
 
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Output, Stdio};
 use std::sync::{Arc, Mutex};
@@ -131,64 +131,82 @@ impl Workspace {
         for (key, value) in env {
             command.env(key, value);
         }
-        let mut child = command
-            .args(args)
-            .args(["--port", "0"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .expect("spawning sqlnow");
-
-        // Both streams are drained by threads for two reasons: a full pipe
-        // would block the server, and output keeps coming after it is ready —
-        // the deep link is printed *after* the address, so a reader that
-        // stopped at readiness would never see it.
-        let printed = Arc::new(Mutex::new(String::new()));
-        let (sender, receiver) = std::sync::mpsc::channel();
-
-        let stdout = child.stdout.take().expect("piped stdout");
-        let collected = printed.clone();
-        std::thread::spawn(move || {
-            let mut announced = false;
-            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
-                if let Some(url) = line.strip_prefix("Server running on ") {
-                    if !announced {
-                        announced = true;
-                        let _ = sender.send(Some(url.trim().to_string()));
-                    }
-                }
-                let mut buffer = collected.lock().expect("output lock");
-                buffer.push_str(&line);
-                buffer.push('\n');
-            }
-            if !announced {
-                let _ = sender.send(None);
-            }
-        });
+        command.args(args);
+        launch(command, None)
+    }
 
-        let stderr = child.stderr.take().expect("piped stderr");
-        let collected = printed.clone();
-        std::thread::spawn(move || {
-            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
-                let mut buffer = collected.lock().expect("output lock");
-                buffer.push_str(&line);
-                buffer.push('\n');
-            }
-        });
-
-        match receiver.recv_timeout(STARTUP) {
-            Ok(Some(url)) => Server { child, url, printed },
-            Ok(None) => {
-                let _ = child.wait();
-                panic!(
-                    "sqlnow exited before listening:\n{}",
-                    printed.lock().expect("output lock")
-                );
-            }
-            Err(_) => {
-                let _ = child.kill();
-                panic!("sqlnow did not start within {:?}", STARTUP);
+    /// The same, with data piped in on stdin — for an input of `-`.
+    pub fn start_with_stdin(&self, input: &str, args: &[&str]) -> Server {
+        let mut command = self.command();
+        command.args(args);
+        launch(command, Some(input))
+    }
+}
+
+/// Start a prepared command and wait until it is listening.
+fn launch(mut command: Command, stdin: Option<&str>) -> Server {
+    let mut child = command
+        .args(["--port", "0"])
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::inherit() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawning sqlnow");
+    // all of it, and closed: sqlnow reads to the end before it starts
+    if let Some(input) = stdin {
+        let mut pipe = child.stdin.take().expect("piped stdin");
+        pipe.write_all(input.as_bytes()).expect("writing to sqlnow's stdin");
+    }
+
+    // Both streams are drained by threads for two reasons: a full pipe
+    // would block the server, and output keeps coming after it is ready —
+    // the deep link is printed *after* the address, so a reader that
+    // stopped at readiness would never see it.
+    let printed = Arc::new(Mutex::new(String::new()));
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let stdout = child.stdout.take().expect("piped stdout");
+    let collected = printed.clone();
+    std::thread::spawn(move || {
+        let mut announced = false;
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(url) = line.strip_prefix("Server running on ") {
+                if !announced {
+                    announced = true;
+                    let _ = sender.send(Some(url.trim().to_string()));
+                }
             }
+            let mut buffer = collected.lock().expect("output lock");
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+        if !announced {
+            let _ = sender.send(None);
+        }
+    });
+
+    let stderr = child.stderr.take().expect("piped stderr");
+    let collected = printed.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let mut buffer = collected.lock().expect("output lock");
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+    });
+
+    match receiver.recv_timeout(STARTUP) {
+        Ok(Some(url)) => Server { child, url, printed },
+        Ok(None) => {
+            let _ = child.wait();
+            panic!(
+                "sqlnow exited before listening:\n{}",
+                printed.lock().expect("output lock")
+            );
+        }
+        Err(_) => {
+            let _ = child.kill();
+            panic!("sqlnow did not start within {:?}", STARTUP);
         }
     }
 }
@@ -314,7 +332,8 @@ impl Server {
             .expect("reading the body")
     }
 
-    /// Watch this session's change stream for a while, counting what it reports.
+    /// Watch this session's change stream for a while, counting the messages
+    /// that make an open grid re-run — `changed` and `refreshed` alike.
     ///
     /// Returned as a handle so a test can watch two servers at once and see
     /// which of them was told about a change.
@@ -340,7 +359,7 @@ impl Server {
                     Err(_) => continue,
                 }
             }
-            seen.matches("data: changed").count()
+            seen.matches("data: ").count()
         })
     }
 
@@ -392,6 +411,16 @@ impl Server {
         serde_json::from_str(&body).expect("query.json returns json")
     }
 
+    /// Run SQL and ask for a profile of its whole result too.
+    pub fn query_profiled(&self, sql: &str) -> Value {
+        let body = ureq::post(&format!("{}/query.json", self.url))
+            .send_form(&[("sql", sql), ("display_limit", "500"), ("profile", "true")])
+            .expect("query failed")
+            .into_string()
+            .expect("reading the body");
+        serde_json::from_str(&body).expect("query.json returns json")
+    }
+
     /// The names in the sidebar, sorted — the single most useful assertion.
     pub fn tables(&self) -> Vec<String> {
         let body = ureq::post(&format!("{}/tables.json", self.url))
@@ -28,6 +28,35 @@ fn only_and_except_choose_tables_from_a_database() {
     assert_eq!(again.tables(), ["legacy.owners", "legacy.units"]);
 }
 
+#[test]
+fn filters_and_the_schemas_option_tell_schemas_apart() {
+    let space = Workspace::new("filters-schemas");
+    let warehouse = space.path().join("warehouse.duckdb");
+    space.exec(
+        &warehouse,
+        "CREATE SCHEMA audit; CREATE SCHEMA sales;
+         CREATE TABLE main.users AS SELECT 1 AS id;
+         CREATE TABLE audit.users AS SELECT 1 AS id;
+         CREATE TABLE audit.logins AS SELECT 1 AS id;
+         CREATE TABLE sales.orders AS SELECT 1 AS id;",
+    );
+    let uri = warehouse.to_string_lossy().to_string();
+
+    // a pattern that starts with one of the database's schemas is matched
+    // against schema.table, so the two users tables are told apart, and a
+    // whole schema can be picked
+    let picked = space.start(&["-v", &uri, "--as", "wh", "--only", "main.users", "--only", "sales.*"]);
+    assert_eq!(picked.tables(), ["wh.sales.orders", "wh.users"]);
+    picked.stop();
+    let dropped = space.start(&["-v", &uri, "--as", "wh", "--only", "users|logins|orders", "--except", "audit.*"]);
+    assert_eq!(dropped.tables(), ["wh.sales.orders", "wh.users"]);
+    dropped.stop();
+
+    // and the schemas option keeps to the schemas it names
+    let chosen = space.start(&["-v", &uri, "--as", "wh", "--with", "schemas=audit,sales"]);
+    assert_eq!(chosen.tables(), ["wh.audit.logins", "wh.audit.users", "wh.sales.orders"]);
+}
+
 #[test]
 fn json_files_can_be_loaded_as_tables() {
     let space = Workspace::new("json-inputs");
@@ -49,6 +78,248 @@ fn json_files_can_be_loaded_as_tables() {
     assert_eq!(server.query("SELECT count(*) FROM units")["table_data"]["rows"][0][0], "2");
 }
 
+#[test]
+fn json_files_can_be_read_in_place_as_views() {
+    let space = Workspace::new("json-views");
+    let object = space.write("plants.json", r#"[{"name": "Plant A", "co2": 120, "site": {"country": "FR"}}]"#);
+    let lines = space.write("units.jsonl", "{\"name\": \"Unit 1\", \"mw\": 50}\n");
+
+    let server = space.start(&[
+        "-v",
+        &object.to_string_lossy(),
+        "-v",
+        &lines.to_string_lossy(),
+    ]);
+    assert_eq!(server.tables(), ["plants", "units"]);
+    // nested objects stay structs rather than being flattened into tables
+    assert_eq!(server.query("SELECT site.country FROM plants")["table_data"]["rows"][0][0], "FR");
+
+    // a view reads the file on every query, so a line appended to a log is
+    // there on the next run without restarting anything
+    std::fs::write(&lines, "{\"name\": \"Unit 1\", \"mw\": 50}\n{\"name\": \"Unit 2\", \"mw\": 70}\n").unwrap();
+    assert_eq!(server.query("SELECT count(*) FROM units")["table_data"]["rows"][0][0], "2");
+
+    // and one can be added while the server runs, like any other view
+    let more = space.write("more.jsonl", "{\"id\": 1}\n");
+    let created = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": more.to_string_lossy(), "kind": "view"}),
+    );
+    assert_eq!(created.0, 201, "{}", created.1);
+    assert_eq!(server.query("SELECT id FROM more")["table_data"]["rows"][0][0], "1");
+}
+
+#[test]
+fn json_can_be_loaded_whole_rather_than_flattened() {
+    let space = Workspace::new("json-native");
+    let lines = space.write(
+        "events.jsonl",
+        "{\"id\": 1, \"site\": {\"country\": \"FR\"}, \"tags\": [\"a\", \"b\"]}\n",
+    );
+
+    let server = space.start(&[&lines.to_string_lossy(), "--json-mode", "native"]);
+    // one table, rather than a schema of a table per nested list
+    assert_eq!(server.tables(), ["events"]);
+    let row = &server.query("SELECT site.country, len(tags) FROM events")["table_data"]["rows"][0];
+    assert_eq!(row[0], "FR");
+    assert_eq!(row[1], "2");
+
+    let inputs = server.get("/api/inputs");
+    assert_eq!(inputs["inputs"][0]["options"]["json_mode"], "native", "{}", inputs);
+}
+
+#[test]
+fn a_partitioned_directory_is_one_view() {
+    let space = Workspace::new("datasets");
+    for (partition, contents) in [
+        ("year=2023", "name,co2\nPlant A,120\n"),
+        // a later file gained a column: matched by name, not by position
+        ("year=2024", "name,co2,region\nPlant A,110,north\nPlant B,340,south\n"),
+    ] {
+        std::fs::create_dir_all(space.path().join("sales").join(partition)).unwrap();
+        space.write(&format!("sales/{}/part-0.csv", partition), contents);
+    }
+    // left behind by whatever wrote the dataset, and not data
+    space.write("sales/_SUCCESS", "");
+    std::fs::create_dir_all(space.path().join("units")).unwrap();
+    space.write("units/a.csv", "name,mw\nUnit 1,50\n");
+    space.write("units/b.csv", "name,mw\nUnit 2,70\n");
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    // compressed parts are the format under their suffix: csv, not "gz"
+    std::fs::create_dir_all(space.path().join("readings")).unwrap();
+    for (part, reading) in [(0, 12), (1, 15)] {
+        let file = space.path().join(format!("readings/part-{}.csv.gz", part));
+        space.exec(
+            &space.path().join("work.sqlnow"),
+            &format!("COPY (SELECT {} AS reading) TO '{}' (FORMAT csv, COMPRESSION gzip)", reading, file.display()),
+        );
+    }
+    let server = space.start(&["work.sqlnow", "sales", "units/*.csv", "readings"]);
+    assert_eq!(server.tables(), ["readings", "sales", "units"]);
+    assert_eq!(server.query("SELECT sum(reading) FROM readings")["table_data"]["rows"][0][0], "27");
+    // the partition directories are columns like any other
+    let by_year = server.query("SELECT year, count(*), count(region) FROM sales GROUP BY year ORDER BY year");
+    assert_eq!(by_year["table_data"]["rows"][0], serde_json::json!(["2023", "1", "0"]));
+    assert_eq!(by_year["table_data"]["rows"][1], serde_json::json!(["2024", "2", "2"]));
+    assert_eq!(server.query("SELECT count(*) FROM units")["table_data"]["rows"][0][0], "2");
+    server.stop();
+
+    // both are recorded as given and replay from the session file alone
+    let again = space.start(&["work.sqlnow"]);
+    assert_eq!(again.tables(), ["readings", "sales", "units"]);
+    assert_eq!(again.query("SELECT count(*) FROM sales")["table_data"]["rows"][0][0], "3");
+}
+
+#[test]
+fn reader_options_are_kept_with_the_input() {
+    let space = Workspace::new("reader-options");
+    space.write(
+        "sales.txt",
+        "exported by the till\n001|2024-03-01|12.50\n002|2024-03-02|7.25\n",
+    );
+    space.write("units.csv", "name;mw\nUnit 1;50\n");
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&[
+        "work.sqlnow",
+        "-v", "sales.txt",
+        "--with", "skip=1",
+        "--with", "header=false",
+        "--with", "delim=|",
+        "--with", "types=column0:VARCHAR",
+    ]);
+    // the leading zeros survive because the id column was told to be text
+    let first = server.query("SELECT column0, column2 FROM sales ORDER BY column0");
+    assert_eq!(first["table_data"]["rows"][0], serde_json::json!(["001", "12.5"]));
+
+    let created = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "units.csv", "options": {"delim": ";"}}),
+    );
+    assert_eq!(created.0, 201, "{}", created.1);
+    assert_eq!(server.query("SELECT mw FROM units")["table_data"]["rows"][0][0], "50");
+    // an option that does not exist is refused rather than ignored
+    let refused = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "units.csv", "as": "again", "options": {"delimeter": ";"}}),
+    );
+    assert_eq!(refused.0, 400);
+    // and so is a type that is not one, which would end up in a table's DDL
+    let refused = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "units.csv", "as": "again", "options": {"types": {"mw": "INTEGER); DROP TABLE sales; --"}}}),
+    );
+    assert_eq!(refused.0, 400, "{}", refused.1);
+    assert!(refused.1["error"].as_str().unwrap().contains("is not one"), "{}", refused.1);
+    server.stop();
+
+    // both replay from the session file read the same way
+    let again = space.start(&["work.sqlnow"]);
+    let first = again.query("SELECT column0 FROM sales ORDER BY column0");
+    assert_eq!(first["table_data"]["rows"][0][0], "001");
+    assert_eq!(again.query("SELECT mw FROM units")["table_data"]["rows"][0][0], "50");
+}
+
+#[test]
+fn each_input_is_read_as_text_or_typed_by_its_own_choice() {
+    let space = Workspace::new("text-or-typed");
+    let ledger = space.path().join("ledger.sqlite");
+    rusqlite::Connection::open(&ledger)
+        .and_then(|db| db.execute_batch("CREATE TABLE entries(amount INTEGER); INSERT INTO entries VALUES (12);"))
+        .expect("sqlite fixture");
+    let notes = space.sqlite("notes.sqlite", &["notes"]);
+    space.write("codes.csv", "code\n007\n");
+    space.write("counts.csv", "count\n7\n");
+    let ledger = ledger.to_string_lossy().to_string();
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&[
+        "work.sqlnow",
+        "-x",
+        "-v", "codes.csv",
+        "-v", "counts.csv", "--with", "text=false",
+        "-v", &ledger, "--as", "ledger", "--with", "text=false",
+    ]);
+    let types = "SELECT typeof(code), typeof(count), typeof(amount) \
+                 FROM codes, counts, ledger.entries";
+    let expected = serde_json::json!(["VARCHAR", "BIGINT", "BIGINT"]);
+    assert_eq!(server.query(types)["table_data"]["rows"][0], expected);
+    assert_eq!(server.query("SELECT code FROM codes")["table_data"]["rows"][0][0], "007");
+
+    // a second SQLite database read the other way sits beside it, each one
+    // read as it asks
+    let (status, created) = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": notes.to_string_lossy(), "as": "notes"}),
+    );
+    assert_eq!(status, 201, "{}", created);
+    let both = "SELECT typeof(amount), typeof(name) FROM ledger.entries, notes.notes";
+    let both_expected = serde_json::json!(["BIGINT", "VARCHAR"]);
+    assert_eq!(server.query(both)["table_data"]["rows"][0], both_expected);
+    assert_eq!(server.query(types)["table_data"]["rows"][0], expected);
+    server.stop();
+
+    // the choices are recorded, so they hold without the flag
+    let again = space.start(&["work.sqlnow"]);
+    assert_eq!(again.query(types)["table_data"]["rows"][0], expected);
+    assert_eq!(again.query(both)["table_data"]["rows"][0], both_expected);
+    again.stop();
+
+    // and a session recorded before inputs kept their own choice still
+    // reads its CSV files as text with -x
+    space.run(&["exec", "old.sqlnow", "SELECT 1"]);
+    let codes = space.path().join("codes.csv").to_string_lossy().to_string();
+    space.run(&[
+        "exec",
+        "old.sqlnow",
+        &format!("INSERT INTO inputs(session, kind, name, uri) SELECT id, 'view', 'old_codes', '{}' FROM sessions", codes),
+    ]);
+    let old = space.start(&["old.sqlnow", "-x"]);
+    assert_eq!(old.query("SELECT typeof(code) FROM old_codes")["table_data"]["rows"][0][0], "VARCHAR");
+}
+
+#[test]
+fn lines_that_do_not_parse_are_set_aside() {
+    let space = Workspace::new("rejects");
+    let bad = "id,amount\n1,10\n2,ten\n3,30\n";
+    space.write("sales.csv", bad);
+    space.write("more.csv", bad);
+
+    // without the option one bad line fails the whole file
+    let out = space.run(&["-v", "sales.csv", "--with", "types=amount:INTEGER", "--port", "0"]);
+    assert!(!out.status.success());
+
+    let server = space.start(&[
+        "-v", "sales.csv",
+        "--with", "types=amount:INTEGER",
+        "--with", "rejects=true",
+    ]);
+    assert_eq!(server.tables(), ["sales", "sales_rejects"]);
+    assert_eq!(server.query("SELECT sum(amount) FROM sales")["table_data"]["rows"][0][0], "40");
+    let rejected = server.query("SELECT file, csv_line, reason FROM sales_rejects");
+    let row = &rejected["table_data"]["rows"][0];
+    assert!(row[0].as_str().unwrap().ends_with("sales.csv"), "{}", row);
+    assert_eq!(row[1], "2,ten");
+    assert!(!row[2].as_str().unwrap().is_empty());
+
+    // over the API, the count comes back with the input
+    let (status, created) = server.post_json(
+        "/api/inputs",
+        serde_json::json!({
+            "uri": "more.csv",
+            "kind": "table",
+            "options": {"types": {"amount": "INTEGER"}, "rejects": true},
+        }),
+    );
+    assert_eq!(status, 201, "{}", created);
+    assert_eq!(created["rejected"], 1);
+
+    // and detaching the input takes its rejects with it
+    assert_eq!(server.delete("/api/inputs/more"), 204);
+    assert_eq!(server.tables(), ["sales", "sales_rejects"]);
+}
+
 #[test]
 fn a_json_file_with_multibyte_text_loads() {
     let space = Workspace::new("json-multibyte");
@@ -82,3 +353,462 @@ fn a_url_without_a_name_is_refused_clearly() {
     assert!(complaint.contains("needs a name"), "{}", complaint);
     assert!(!complaint.contains("zero-length"), "the parser error leaked out: {}", complaint);
 }
+
+#[test]
+fn a_table_input_is_refreshed_on_request_or_when_its_file_changes() {
+    let space = Workspace::new("refresh");
+    space.write("sales.csv", "id,amount\n1,10\n2,20\n");
+    space.write("live.csv", "id\n1\n");
+
+    let server = space.start(&["-t", "sales.csv", "-v", "live.csv"]);
+    space.write("sales.csv", "id,amount\n1,10\n2,20\n3,30\n");
+    // a table keeps what it loaded until it is asked to read again
+    assert_eq!(server.query("SELECT count(*) FROM sales")["table_data"]["rows"][0][0], "2");
+
+    let watching = server.watch_changes(std::time::Duration::from_secs(3));
+    std::thread::sleep(std::time::Duration::from_millis(600));
+    let (status, refreshed) = server.post_json("/api/inputs/sales/refresh", serde_json::json!({}));
+    assert_eq!(status, 200, "{}", refreshed);
+    assert_eq!(server.query("SELECT count(*) FROM sales")["table_data"]["rows"][0][0], "3");
+    assert!(watching.join().expect("watcher thread") >= 1, "the grid was not told to re-run");
+
+    // a view is never stale, and there is nothing else to refresh
+    let (status, _) = server.post_json("/api/inputs/live/refresh", serde_json::json!({}));
+    assert_eq!(status, 404);
+    let (status, _) = server.post_json("/api/inputs/nothing/refresh", serde_json::json!({}));
+    assert_eq!(status, 404);
+
+    // with --watch, changing the file is enough
+    let watched = space.start(&["-t", "sales.csv", "--watch"]);
+    space.write("sales.csv", "id,amount\n1,10\n");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while watched.query("SELECT count(*) FROM sales")["table_data"]["rows"][0][0] != "1" {
+        assert!(std::time::Instant::now() < deadline, "the changed file was never reloaded");
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn a_view_can_be_defined_by_sql_over_the_other_inputs() {
+    let space = Workspace::new("derived");
+    space.write("raw.csv", "id,total\n1,10\n2,-5\n3,30\n");
+
+    // named before what it reads, and made after it all the same
+    let server = space.start(&["-v", "clean=SELECT * FROM raw WHERE total > 0", "raw.csv"]);
+    assert_eq!(server.tables(), ["clean", "raw"]);
+    assert_eq!(server.query("SELECT count(*) FROM clean")["table_data"]["rows"][0][0], "2");
+
+    let (status, created) = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"name": "big", "sql": "SELECT * FROM clean WHERE total > 20"}),
+    );
+    assert_eq!(status, 201, "{}", created);
+    assert_eq!(created["kind"], "sql");
+    assert_eq!(server.query("SELECT id FROM big")["table_data"]["rows"][0][0], "3");
+
+    // SQL that does not bind is refused, and so is a name it has to be given
+    let (status, _) = server.post_json("/api/inputs", serde_json::json!({"name": "bad", "sql": "SELECT * FROM nowhere"}));
+    assert_eq!(status, 400);
+    let (status, _) = server.post_json("/api/inputs", serde_json::json!({"sql": "SELECT 1"}));
+    assert_eq!(status, 400);
+    // and so is a second statement after the query, which never runs
+    let (status, _) = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"name": "sly", "sql": "SELECT * FROM raw; DROP TABLE raw"}),
+    );
+    assert_eq!(status, 400);
+    assert_eq!(server.query("SELECT count(*) FROM raw")["table_data"]["rows"][0][0], "3");
+
+    let recorded = server.get("/api/inputs");
+    let sql: Vec<&str> = recorded["inputs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|input| input["kind"] == "sql")
+        .map(|input| input["sql"].as_str().unwrap())
+        .collect();
+    assert_eq!(sql, ["SELECT * FROM raw WHERE total > 0", "SELECT * FROM clean WHERE total > 20"]);
+    server.stop();
+
+    // the same inputs come back to the same session, definitions and all
+    let again = space.start(&["raw.csv"]);
+    assert_eq!(again.tables(), ["big", "clean", "raw"]);
+}
+
+#[test]
+fn data_piped_in_on_stdin_is_loaded_as_a_table() {
+    let space = Workspace::new("stdin");
+
+    let server = space.start_with_stdin(
+        "{\"id\": 1, \"name\": \"a\"}\n{\"id\": 2, \"name\": \"b\"}\n",
+        &["-", "--as", "results"],
+    );
+    assert_eq!(server.tables(), ["results"]);
+    assert_eq!(server.query("SELECT count(*) FROM results")["table_data"]["rows"][0][0], "2");
+    // recorded as coming from stdin, with nothing left behind to refresh from
+    let recorded = server.get("/api/inputs");
+    assert_eq!(recorded["inputs"][0]["uri"], "-");
+    let (status, _) = server.post_json("/api/inputs/results/refresh", serde_json::json!({}));
+    assert_eq!(status, 404);
+    server.stop();
+
+    let server = space.start_with_stdin("id\tscore\n1\t9.5\n2\t7\n", &["-"]);
+    assert_eq!(server.query("SELECT sum(score) FROM stdin")["table_data"]["rows"][0][0], "16.5");
+    server.stop();
+
+    // an empty pipe says so rather than starting on nothing
+    let out = space.run(&["-"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("stdin"));
+
+    // and the session it made says why it cannot come back
+    let out = space.run_text(&["--resume", "2"]);
+    assert!(out.contains("piped in on stdin"), "{}", out);
+}
+
+/// Avro's ints and lengths are zigzag varints.
+fn avro_long(value: i64, out: &mut Vec<u8>) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push((zigzag as u8 & 0x7f) | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+fn avro_bytes(value: &[u8], out: &mut Vec<u8>) {
+    avro_long(value.len() as i64, out);
+    out.extend_from_slice(value);
+}
+
+/// An Avro container file written by hand: duckdb reads Avro but cannot
+/// write it, and the layout is small enough — a header of metadata, the
+/// schema among it, then one block of `count` rows, already encoded, between
+/// sync markers.
+fn avro_file(schema: &str, metadata: &[(&str, &str)], count: i64, rows: &[u8]) -> Vec<u8> {
+    let sync = [7u8; 16];
+    let mut file = b"Obj\x01".to_vec();
+    avro_long(metadata.len() as i64 + 2, &mut file);
+    avro_bytes(b"avro.schema", &mut file);
+    avro_bytes(schema.as_bytes(), &mut file);
+    avro_bytes(b"avro.codec", &mut file);
+    avro_bytes(b"null", &mut file);
+    for (key, value) in metadata {
+        avro_bytes(key.as_bytes(), &mut file);
+        avro_bytes(value.as_bytes(), &mut file);
+    }
+    avro_long(0, &mut file);
+    file.extend_from_slice(&sync);
+
+    avro_long(count, &mut file);
+    avro_long(rows.len() as i64, &mut file);
+    file.extend_from_slice(rows);
+    file.extend_from_slice(&sync);
+    file
+}
+
+/// A two-row Avro file with the plants fixture's columns.
+fn plants_avro() -> Vec<u8> {
+    let schema = r#"{"type": "record", "name": "plant", "fields": [
+        {"name": "name", "type": "string"}, {"name": "co2", "type": "long"}]}"#;
+    let mut rows = vec![];
+    for (name, co2) in [("Plant A", 120), ("Plant B", 340)] {
+        avro_bytes(name.as_bytes(), &mut rows);
+        avro_long(co2, &mut rows);
+    }
+    avro_file(schema, &[], 2, &rows)
+}
+
+#[test]
+fn avro_files_are_read_as_views_or_tables() {
+    let space = Workspace::new("avro");
+    std::fs::write(space.path().join("plants.avro"), plants_avro()).unwrap();
+    // a Kafka dump with no extension is told by its header
+    std::fs::write(space.path().join("dump"), plants_avro()).unwrap();
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&["work.sqlnow", "plants.avro", "-t", "dump"]);
+    // named after the file, as any other is
+    assert_eq!(server.tables(), ["dump", "plants"]);
+    let sql = "SELECT name, co2 FROM plants ORDER BY name";
+    assert_eq!(server.query(sql)["table_data"]["rows"][1], serde_json::json!(["Plant B", "340"]));
+    assert_eq!(server.query("SELECT sum(co2) FROM dump")["table_data"]["rows"][0][0], "460");
+    server.stop();
+
+    // the view is recorded, and replays from the session file alone, for the
+    // server and for `sqlnow sql`
+    let again = space.start(&["work.sqlnow"]);
+    assert_eq!(again.query("SELECT count(*) FROM plants")["table_data"]["rows"][0][0], "2");
+    again.stop();
+    let replayed = space.run_text(&["sql", "work.sqlnow", "SELECT sum(co2) AS co2 FROM plants", "-f", "csv"]);
+    assert_eq!(replayed.trim(), "co2\n460");
+}
+
+/// Two plants as an Arrow IPC file.
+fn plants_arrow(path: &std::path::Path) {
+    use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("co2", DataType::Int64, false),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec!["Plant A", "Plant B"])),
+        Arc::new(Int64Array::from(vec![120, 340])),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns).unwrap();
+    let mut writer = arrow_ipc::writer::FileWriter::try_new(std::fs::File::create(path).unwrap(), &schema).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+}
+
+#[test]
+fn arrow_files_are_read_as_views_or_tables() {
+    let space = Workspace::new("arrow");
+    plants_arrow(&space.path().join("plants.arrow"));
+    // Feather version 2 is the same thing, and a file with neither name is
+    // told by its header
+    plants_arrow(&space.path().join("frame.feather"));
+    plants_arrow(&space.path().join("batches"));
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&["work.sqlnow", "plants.arrow", "frame.feather", "-t", "batches"]);
+    assert_eq!(server.tables(), ["batches", "frame", "plants"]);
+    let sql = "SELECT name, co2 FROM plants ORDER BY name";
+    assert_eq!(server.query(sql)["table_data"]["rows"][1], serde_json::json!(["Plant B", "340"]));
+    assert_eq!(server.query("SELECT sum(co2) FROM frame")["table_data"]["rows"][0][0], "460");
+    assert_eq!(server.query("SELECT count(*) FROM batches")["table_data"]["rows"][0][0], "2");
+
+    // read_arrow cannot read a directory of them as one, so it is refused
+    // rather than read without its partition columns
+    std::fs::create_dir_all(space.path().join("batches_by_year/year=2024")).unwrap();
+    plants_arrow(&space.path().join("batches_by_year/year=2024/part-0.arrow"));
+    let refused = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": space.path().join("batches_by_year").to_string_lossy()}),
+    );
+    assert_eq!(refused.0, 400, "{}", refused.1);
+    assert!(refused.1["error"].as_str().unwrap().contains("give each its own -v"), "{}", refused.1);
+    server.stop();
+
+    // the views replay from the session file alone, nanoarrow loaded again
+    // for them, for `sqlnow sql` too
+    let replayed = space.run_text(&["sql", "work.sqlnow", "SELECT sum(co2) AS co2 FROM plants", "-f", "csv"]);
+    assert_eq!(replayed.trim(), "co2\n460");
+}
+
+#[test]
+fn a_delta_table_is_a_view_that_can_travel_back_in_time() {
+    let space = Workspace::new("delta");
+    let table = space.path().join("orders");
+    std::fs::create_dir_all(table.join("_delta_log")).unwrap();
+    // duckdb writes the data files; the log that makes them a table, one
+    // commit per version, is small enough to write by hand
+    space.run(&["exec", "scratch.sqlnow", "SELECT 1"]);
+    let schema = r#"{"type":"struct","fields":[{"name":"name","type":"string","nullable":true,"metadata":{}},{"name":"co2","type":"long","nullable":true,"metadata":{}}]}"#;
+    for (version, (name, co2)) in [("Plant A", 120), ("Plant B", 340)].into_iter().enumerate() {
+        let part = table.join(format!("part-{}.parquet", version));
+        space.exec(
+            &space.path().join("scratch.sqlnow"),
+            &format!(
+                "COPY (SELECT '{}' AS name, {}::BIGINT AS co2) TO '{}' (FORMAT parquet)",
+                name,
+                co2,
+                part.display()
+            ),
+        );
+        let mut commit = vec![];
+        if version == 0 {
+            commit.push(serde_json::json!({"protocol": {"minReaderVersion": 1, "minWriterVersion": 2}}));
+            commit.push(serde_json::json!({"metaData": {
+                "id": "5f8c2e1a-0000-4000-8000-000000000000",
+                "format": {"provider": "parquet", "options": {}},
+                "schemaString": schema,
+                "partitionColumns": [],
+                "configuration": {},
+                "createdTime": 1700000000000u64,
+            }}));
+        }
+        commit.push(serde_json::json!({"add": {
+            "path": format!("part-{}.parquet", version),
+            "partitionValues": {},
+            "size": std::fs::metadata(&part).unwrap().len(),
+            "modificationTime": 1700000000000u64,
+            "dataChange": true,
+        }}));
+        let lines: Vec<String> = commit.iter().map(|action| action.to_string()).collect();
+        space.write(&format!("orders/_delta_log/{:020}.json", version), &(lines.join("\n") + "\n"));
+    }
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&["work.sqlnow", "orders"]);
+    // one view of the table as it is now, not of every parquet file under it
+    assert_eq!(server.tables(), ["orders"]);
+    assert_eq!(server.query("SELECT sum(co2) FROM orders")["table_data"]["rows"][0][0], "460");
+
+    // and another of it as it was, beside the current one
+    let created = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "orders", "as": "orders_v0", "options": {"version": 0}}),
+    );
+    assert_eq!(created.0, 201, "{}", created.1);
+    assert_eq!(server.query("SELECT sum(co2) FROM orders_v0")["table_data"]["rows"][0][0], "120");
+    // a pin is for the format that has one
+    let refused = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "orders", "as": "wrong", "options": {"snapshot": 1}}),
+    );
+    assert_eq!(refused.0, 400);
+    server.stop();
+
+    // the pin is kept with the input
+    let again = space.start(&["work.sqlnow"]);
+    assert_eq!(again.query("SELECT count(*) FROM orders_v0")["table_data"]["rows"][0][0], "1");
+    assert_eq!(again.query("SELECT count(*) FROM orders")["table_data"]["rows"][0][0], "2");
+}
+
+#[test]
+fn an_iceberg_table_is_a_view_read_through_iceberg_scan() {
+    let space = Workspace::new("iceberg");
+    let table = space.path().join("events");
+    std::fs::create_dir_all(table.join("data")).unwrap();
+    std::fs::create_dir_all(table.join("metadata")).unwrap();
+    let at = |path: &str| table.join(path).to_string_lossy().to_string();
+
+    // duckdb writes the data file, with the field ids the table's schema
+    // names its columns by; the manifests and metadata above it are written
+    // here, one snapshot that adds it
+    space.run(&["exec", "scratch.sqlnow", "SELECT 1"]);
+    space.exec(
+        &space.path().join("scratch.sqlnow"),
+        &format!(
+            "COPY (SELECT * FROM (VALUES ('Plant A', 120::BIGINT), ('Plant B', 340::BIGINT)) AS t(name, co2)) \
+             TO '{}' (FORMAT parquet, FIELD_IDS {{name: 1, co2: 2}})",
+            at("data/part-0.parquet")
+        ),
+    );
+    let data_size = std::fs::metadata(at("data/part-0.parquet")).unwrap().len() as i64;
+    let schema = serde_json::json!({"type": "struct", "schema-id": 0, "fields": [
+        {"id": 1, "name": "name", "required": false, "type": "string"},
+        {"id": 2, "name": "co2", "required": false, "type": "long"},
+    ]});
+    let optional_long = r#"["null", "long"]"#;
+
+    let entry_schema = format!(
+        r#"{{"type": "record", "name": "manifest_entry", "fields": [
+            {{"name": "status", "type": "int", "field-id": 0}},
+            {{"name": "snapshot_id", "type": {0}, "field-id": 1}},
+            {{"name": "sequence_number", "type": {0}, "field-id": 3}},
+            {{"name": "file_sequence_number", "type": {0}, "field-id": 4}},
+            {{"name": "data_file", "field-id": 2, "type": {{"type": "record", "name": "r2", "fields": [
+                {{"name": "content", "type": "int", "field-id": 134}},
+                {{"name": "file_path", "type": "string", "field-id": 100}},
+                {{"name": "file_format", "type": "string", "field-id": 101}},
+                {{"name": "partition", "type": {{"type": "record", "name": "r102", "fields": []}}, "field-id": 102}},
+                {{"name": "record_count", "type": "long", "field-id": 103}},
+                {{"name": "file_size_in_bytes", "type": "long", "field-id": 104}}
+            ]}}}}
+        ]}}"#,
+        optional_long
+    );
+    let mut entry = vec![];
+    avro_long(1, &mut entry); // added
+    for value in [1, 1, 1] {
+        // the union's second branch, a long
+        avro_long(1, &mut entry);
+        avro_long(value, &mut entry);
+    }
+    avro_long(0, &mut entry); // data
+    avro_bytes(at("data/part-0.parquet").as_bytes(), &mut entry);
+    avro_bytes(b"PARQUET", &mut entry);
+    avro_long(2, &mut entry);
+    avro_long(data_size, &mut entry);
+    let schema_text = schema.to_string();
+    let manifest = avro_file(
+        &entry_schema,
+        &[
+            ("schema", schema_text.as_str()),
+            ("partition-spec", "[]"),
+            ("partition-spec-id", "0"),
+            ("format-version", "2"),
+            ("content", "data"),
+        ],
+        1,
+        &entry,
+    );
+    std::fs::write(at("metadata/manifest-0.avro"), &manifest).unwrap();
+
+    let list_schema = r#"{"type": "record", "name": "manifest_file", "fields": [
+        {"name": "manifest_path", "type": "string", "field-id": 500},
+        {"name": "manifest_length", "type": "long", "field-id": 501},
+        {"name": "partition_spec_id", "type": "int", "field-id": 502},
+        {"name": "content", "type": "int", "field-id": 517},
+        {"name": "sequence_number", "type": "long", "field-id": 515},
+        {"name": "min_sequence_number", "type": "long", "field-id": 516},
+        {"name": "added_snapshot_id", "type": "long", "field-id": 503},
+        {"name": "added_files_count", "type": "int", "field-id": 504},
+        {"name": "existing_files_count", "type": "int", "field-id": 505},
+        {"name": "deleted_files_count", "type": "int", "field-id": 506},
+        {"name": "added_rows_count", "type": "long", "field-id": 512},
+        {"name": "existing_rows_count", "type": "long", "field-id": 513},
+        {"name": "deleted_rows_count", "type": "long", "field-id": 514}
+    ]}"#;
+    let mut listed = vec![];
+    avro_bytes(at("metadata/manifest-0.avro").as_bytes(), &mut listed);
+    for value in [manifest.len() as i64, 0, 0, 1, 1, 1, 1, 0, 0, 2, 0, 0] {
+        avro_long(value, &mut listed);
+    }
+    std::fs::write(at("metadata/snap-1.avro"), avro_file(list_schema, &[("format-version", "2")], 1, &listed)).unwrap();
+
+    let metadata = serde_json::json!({
+        "format-version": 2,
+        "table-uuid": "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+        "location": table.to_string_lossy(),
+        "last-sequence-number": 1,
+        "last-updated-ms": 1700000000000u64,
+        "last-column-id": 2,
+        "current-schema-id": 0,
+        "schemas": [schema],
+        "default-spec-id": 0,
+        "partition-specs": [{"spec-id": 0, "fields": []}],
+        "last-partition-id": 999,
+        "default-sort-order-id": 0,
+        "sort-orders": [{"order-id": 0, "fields": []}],
+        "properties": {},
+        "current-snapshot-id": 1,
+        "snapshots": [{
+            "snapshot-id": 1,
+            "sequence-number": 1,
+            "timestamp-ms": 1700000000000u64,
+            "summary": {"operation": "append"},
+            "manifest-list": at("metadata/snap-1.avro"),
+            "schema-id": 0,
+        }],
+        "snapshot-log": [{"snapshot-id": 1, "timestamp-ms": 1700000000000u64}],
+        "metadata-log": [],
+    });
+    space.write("events/metadata/v1.metadata.json", &metadata.to_string());
+
+    space.run(&["exec", "work.sqlnow", "SELECT 1"]);
+    let server = space.start(&["work.sqlnow", "events"]);
+    // one view of the table, not a dataset of the parquet under data/
+    assert_eq!(server.tables(), ["events"]);
+    let sql = "SELECT name, co2 FROM events ORDER BY name";
+    assert_eq!(server.query(sql)["table_data"]["rows"][1], serde_json::json!(["Plant B", "340"]));
+    // and the scan it is read by is iceberg's own
+    let scan = server.query("SELECT sql FROM duckdb_views() WHERE view_name = 'events'");
+    assert!(scan["table_data"]["rows"][0][0].as_str().unwrap().contains("iceberg_scan"), "{}", scan);
+    // the snapshot can be pinned, in a view beside the current one
+    let created = server.post_json(
+        "/api/inputs",
+        serde_json::json!({"uri": "events", "as": "events_1", "options": {"snapshot": 1}}),
+    );
+    assert_eq!(created.0, 201, "{}", created.1);
+    assert_eq!(server.query("SELECT sum(co2) FROM events_1")["table_data"]["rows"][0][0], "460");
+    server.stop();
+
+    let again = space.start(&["work.sqlnow"]);
+    assert_eq!(again.query("SELECT count(*) FROM events")["table_data"]["rows"][0][0], "2");
+}